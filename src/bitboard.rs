@@ -0,0 +1,239 @@
+use board::{InvalidColumnError, Token};
+
+/// A set of cells, one bit per cell, indexed by `col * rows + row`.
+pub trait Mask: Clone {
+    fn empty(cells: usize) -> Self;
+    fn get(&self, index: usize) -> bool;
+    fn set(&mut self, index: usize);
+    fn unset(&mut self, index: usize);
+}
+
+impl Mask for u64 {
+    fn empty(_: usize) -> u64 {
+        0
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> bool {
+        self & (1 << index) != 0
+    }
+
+    #[inline]
+    fn set(&mut self, index: usize) {
+        *self |= 1 << index
+    }
+
+    #[inline]
+    fn unset(&mut self, index: usize) {
+        *self &= !(1 << index)
+    }
+}
+
+/// Fallback mask for boards with more than 64 cells.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct WideMask(Box<[u64]>);
+
+impl Mask for WideMask {
+    fn empty(cells: usize) -> WideMask {
+        WideMask(vec![0; cells.div_ceil(64)].into_boxed_slice())
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> bool {
+        self.0[index / 64].get(index % 64)
+    }
+
+    #[inline]
+    fn set(&mut self, index: usize) {
+        self.0[index / 64].set(index % 64)
+    }
+
+    #[inline]
+    fn unset(&mut self, index: usize) {
+        self.0[index / 64].unset(index % 64)
+    }
+}
+
+fn player_index(token: Token) -> usize {
+    match token {
+        Token::Player1 => 0,
+        Token::Player2 => 1,
+    }
+}
+
+// vertical, horizontal, diagonal (bottom-left to top-right), diagonal (top-left to bottom-right)
+const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+#[derive(Clone)]
+pub struct Bitboard<M: Mask> {
+    cols: usize,
+    rows: usize,
+
+    masks: [M; 2],
+    heights: Box<[usize]>,
+}
+
+impl<M: Mask> Bitboard<M> {
+    pub fn new(cols: usize, rows: usize) -> Bitboard<M> {
+        Bitboard {
+            cols,
+            rows,
+            masks: [M::empty(cols * rows), M::empty(cols * rows)],
+            heights: vec![0; cols].into_boxed_slice(),
+        }
+    }
+
+    #[inline]
+    fn index(&self, col: usize, row: usize) -> usize {
+        col * self.rows + row
+    }
+
+    pub fn height(&self, col: usize) -> usize {
+        self.heights[col]
+    }
+
+    pub fn token_at(&self, col: usize, row: usize) -> Option<Token> {
+        let index = self.index(col, row);
+        if self.masks[0].get(index) {
+            Some(Token::Player1)
+        } else if self.masks[1].get(index) {
+            Some(Token::Player2)
+        } else {
+            None
+        }
+    }
+
+    fn owned_by(&self, col: isize, row: isize, player: usize) -> bool {
+        col >= 0 && row >= 0
+            && (col as usize) < self.cols && (row as usize) < self.rows
+            && self.masks[player].get(self.index(col as usize, row as usize))
+    }
+
+    pub fn drop(&mut self, col: usize, token: Token) -> Result<usize, InvalidColumnError> {
+        if col >= self.cols || self.heights[col] >= self.rows {
+            return Err(InvalidColumnError)
+        }
+
+        let row = self.heights[col];
+        let index = self.index(col, row);
+        self.masks[player_index(token)].set(index);
+        self.heights[col] += 1;
+
+        Ok(row)
+    }
+
+    /// Removes the top token of `col` and returns the row it was taken from.
+    pub fn pop(&mut self, col: usize) -> Option<usize> {
+        if col >= self.cols || self.heights[col] == 0 {
+            return None
+        }
+
+        self.heights[col] -= 1;
+        let row = self.heights[col];
+        let index = self.index(col, row);
+        self.masks[0].unset(index);
+        self.masks[1].unset(index);
+
+        Some(row)
+    }
+
+    /// Returns the cells of a line of `win_len` tokens passing through `(col, row)`, if any.
+    pub fn winning_line(&self, token: Token, win_len: usize, col: usize, row: usize) -> Option<Box<[(usize, usize)]>> {
+        let player = player_index(token);
+        let (col, row) = (col as isize, row as isize);
+
+        for (dc, dr) in DIRECTIONS.iter() {
+            let mut back = 0;
+            while self.owned_by(col - dc * (back + 1), row - dr * (back + 1), player) {
+                back += 1;
+            }
+
+            let mut forward = 0;
+            while self.owned_by(col + dc * (forward + 1), row + dr * (forward + 1), player) {
+                forward += 1;
+            }
+
+            if (back + forward + 1) as usize >= win_len {
+                let (start_col, start_row) = (col - dc * back, row - dr * back);
+                return Some(
+                    (0..win_len as isize)
+                        .map(|i| ((start_col + dc * i) as usize, (start_row + dr * i) as usize))
+                        .collect::<Vec<_>>()
+                        .into_boxed_slice(),
+                )
+            }
+        }
+
+        None
+    }
+
+    pub fn full(&self) -> bool {
+        self.heights.iter().all(|height| *height >= self.rows)
+    }
+
+    pub fn stones(&self) -> usize {
+        self.heights.iter().sum()
+    }
+}
+
+/// Bit-packed board used by `Game`; picks the narrowest mask that fits the board.
+#[derive(Clone)]
+pub enum Position {
+    Narrow(Bitboard<u64>),
+    Wide(Bitboard<WideMask>),
+}
+
+macro_rules! dispatch {
+    ($self:expr, $board:ident => $body:expr) => {
+        match $self {
+            Position::Narrow($board) => $body,
+            Position::Wide($board) => $body,
+        }
+    };
+}
+
+impl Position {
+    pub fn new(cols: usize, rows: usize) -> Position {
+        if cols * rows <= 64 {
+            Position::Narrow(Bitboard::new(cols, rows))
+        } else {
+            Position::Wide(Bitboard::new(cols, rows))
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        dispatch!(self, board => board.cols)
+    }
+
+    pub fn rows(&self) -> usize {
+        dispatch!(self, board => board.rows)
+    }
+
+    pub fn height(&self, col: usize) -> usize {
+        dispatch!(self, board => board.height(col))
+    }
+
+    pub fn token_at(&self, col: usize, row: usize) -> Option<Token> {
+        dispatch!(self, board => board.token_at(col, row))
+    }
+
+    pub fn drop(&mut self, col: usize, token: Token) -> Result<usize, InvalidColumnError> {
+        dispatch!(self, board => board.drop(col, token))
+    }
+
+    pub fn pop(&mut self, col: usize) -> Option<usize> {
+        dispatch!(self, board => board.pop(col))
+    }
+
+    pub fn winning_line(&self, token: Token, win_len: usize, col: usize, row: usize) -> Option<Box<[(usize, usize)]>> {
+        dispatch!(self, board => board.winning_line(token, win_len, col, row))
+    }
+
+    pub fn full(&self) -> bool {
+        dispatch!(self, board => board.full())
+    }
+
+    pub fn stones(&self) -> usize {
+        dispatch!(self, board => board.stones())
+    }
+}
@@ -8,13 +8,20 @@ use std::fmt;
 use wasm_bindgen::prelude::*;
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Token {
     Player1,
     Player2,
 }
 
 impl Token {
+    pub fn other(&self) -> Token {
+        match self {
+            Token::Player1 => Token::Player2,
+            Token::Player2 => Token::Player1,
+        }
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     fn color(&self) -> &dyn termion::color::Color {
         use self::Token::{Player1, Player2};
@@ -42,12 +49,26 @@ pub enum CellState {
     Filled(Token),
 }
 
+/// Uses terminal colors, unless formatted with `{:#}` where the tokens are `x` and `o`
+/// and highlighted cells are in upper case or `+` when empty.
 #[cfg(not(target_arch = "wasm32"))]
 impl fmt::Display for CellState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::CellState::*;
+        use self::Token::{Player1, Player2};
         use termion::color::{Bg, Fg, Yellow, Reset};
 
+        if f.alternate() {
+            return match self {
+                Empty => write!(f, " "),
+                Colored => write!(f, "+"),
+                Filled(Player1) => write!(f, "x"),
+                Filled(Player2) => write!(f, "o"),
+                Highlighted(Player1) => write!(f, "X"),
+                Highlighted(Player2) => write!(f, "O"),
+            }
+        }
+
         match self {
             Empty => write!(f, " "),
             Colored => write!(f, "{} {}", Bg(Yellow), Bg(Reset)),
@@ -218,21 +239,34 @@ struct Row<'a>(&'a Board, usize);
 #[cfg(not(target_arch = "wasm32"))]
 impl <'a> fmt::Display for Row<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for i in 0..self.0.cols - 1 {
-            write!(f, "{}", self.0.cells[i][self.1])?;
-            f.write_str(" | ")?;
+        for i in 0..self.0.cols {
+            if i > 0 {
+                f.write_str(" | ")?;
+            }
+
+            if f.alternate() {
+                write!(f, "{:#}", self.0.cells[i][self.1])?;
+            } else {
+                write!(f, "{}", self.0.cells[i][self.1])?;
+            }
         }
-        write!(f, "{}", self.0.cells[self.0.cols - 1][self.1])
+
+        Ok(())
     }
 }
 
+/// Formatted with `{:#}`, the board is plain text; see `CellState`.
 #[cfg(not(target_arch = "wasm32"))]
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let row_sep = (0..self.cols).map(|_| "----").collect::<Vec<_>>().join("");
         for i in (0..self.rows).rev() {
             writeln!(f, "-{}", row_sep)?;
-            writeln!(f, "| {} |", Row(self, i))?;
+            if f.alternate() {
+                writeln!(f, "| {:#} |", Row(self, i))?;
+            } else {
+                writeln!(f, "| {} |", Row(self, i))?;
+            }
         }
         write!(f, "-{}", row_sep)
     }
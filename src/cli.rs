@@ -1,13 +1,23 @@
+use std::env;
 use std::error;
 use std::io;
 use std::fmt;
 use std::io::prelude::*;
+use std::path::Path;
 use std::str::FromStr;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
+use board::Token;
+use common::Rng;
+use export::Format;
 use game::Game;
+use limits::SearchLimits;
 use mcts::MCTS;
+use record::{self, Record};
+use report::SearchReport;
 use hbot;
+use solver::Solver;
 
 #[derive(Debug)]
 struct Done;
@@ -20,24 +30,27 @@ impl fmt::Display for Done {
 
 impl error::Error for Done {}
 
-fn player_move<F: Fn(&Game) -> ()>(game: &mut Game, input: &mut io::Lines<io::StdinLock<'_>>, print_board: F) -> Result<(usize, usize), Done> {
+// `u` takes back `undo_moves` moves
+fn player_move<F: Fn(&Game) -> ()>(game: &mut Game, input: &mut io::Lines<io::StdinLock<'_>>, undo_moves: usize, color: bool, print_board: F) -> Result<(usize, usize), Done> {
     let mut message: Option<String> = None;
 
     loop {
         if let Some(msg) = message {
-            println!("{}", termion::clear::All);
+            clear_screen(color);
             print_board(game);
             println!("{}", msg);
         } else {
             print_board(game);
         }
 
+        let can_undo = game.history().len() >= undo_moves;
         print!(
-            "What's your move? [{}]: ",
+            "What's your move? [{}{}]: ",
             game.valid_moves().iter()
                 .map(|col| (col + 1).to_string())
                 .collect::<Vec<_>>()
                 .join(","),
+            if can_undo { " or u to undo" } else { "" },
         );
 
         io::stdout().flush().unwrap();
@@ -57,6 +70,20 @@ fn player_move<F: Fn(&Game) -> ()>(game: &mut Game, input: &mut io::Lines<io::St
             message = Some(String::from("please enter a number"));
             continue;
         }
+
+        if line == "u" {
+            if can_undo {
+                for _ in 0..undo_moves {
+                    game.undo();
+                }
+
+                message = Some(String::from("took back your last move"));
+            } else {
+                message = Some(String::from("there is no move to undo"));
+            }
+
+            continue;
+        }
         
         let mut col = match usize::from_str(line.as_ref()) {
             Err(err) => {
@@ -81,34 +108,36 @@ fn player_move<F: Fn(&Game) -> ()>(game: &mut Game, input: &mut io::Lines<io::St
     }
 }
 
-fn mcts_move(game: &mut Game, mcts: &MCTS) -> (usize, usize) {
-    let state = game.state();
-    let valid_moves = game.valid_moves();
-    let winrates = mcts.move_weights(&state, &valid_moves);
+fn mcts_move(game: &mut Game, mcts: &MCTS, report: &SearchReport, verbose: bool) -> (usize, usize) {
+    if verbose {
+        println!("{}\n", report);
+    } else {
+        let valid_moves = game.valid_moves();
+        let winrates = mcts.move_weights(game, &valid_moves);
+
+        // print out move win rates
+        println!(
+            "{}",
+            winrates.iter()
+                .enumerate()
+                .map(|(i, winrate)| format!("{}: {}", valid_moves[i], winrate))
+                .collect::<Vec<_>>()
+                .join("\t"),
+        );
+    }
 
-    // print out move win rates
-    println!(
-        "{}",
-        winrates.iter()
-            .enumerate()
-            .map(|(i, winrate)| format!("{}: {}", valid_moves[i], winrate))
-            .collect::<Vec<_>>()
-            .join("\t"),
-    );
-    
-    let (col, _) = valid_moves.iter()
-        .zip(winrates)
-        .fold((0, -1.0), |(prev_col, prev_weight), (col, weight)| if prev_weight < weight {
-            (*col, weight)
-        } else {
-            (prev_col, prev_weight)
-        });
+    let col = mcts.best_move(game).unwrap();
 
     (col, game.drop(col).unwrap())
 }
 
-fn hbot_move(game: &mut Game) -> (usize, usize) {
-    let col = hbot::next_move(&game);
+fn hbot_move(game: &mut Game, rng: &mut Rng) -> (usize, usize) {
+    let col = hbot::next_move(game, rng);
+    (col, game.drop(col).unwrap())
+}
+
+fn solver_move(game: &mut Game, solver: &mut Solver) -> (usize, usize) {
+    let col = solver.next_move(game);
     (col, game.drop(col).unwrap())
 }
 
@@ -116,32 +145,74 @@ fn print_board_top(cols: usize) {
     println!("| {} |", (1..=cols).map(|i| i.to_string()).collect::<Vec<_>>().join(" | "))
 }
 
-fn print_board(game: &Game, last_move: Option<(usize, usize)>) {
+// highlights the last move, and shows tokens as `x` and `o` without `color`
+fn print_board(game: &Game, color: bool) {
     let mut board = game.board();
-    if let Some((col, row)) = last_move {
-        board.highlight(col, row);
+    if let Some(col) = game.history().last() {
+        board.highlight(*col, game.height(*col) - 1);
     }
 
     print_board_top(game.cols());
-    println!("{}", board);
+    if color {
+        println!("{}", board);
+    } else {
+        println!("{:#}", board);
+    }
 }
 
-enum Opponent {
+fn clear_screen(color: bool) {
+    if color {
+        println!("{}", termion::clear::All);
+    } else {
+        println!();
+    }
+}
+
+// who plays a side of the game
+#[derive(Copy, Clone, PartialEq)]
+enum Player {
     MCTS,
     HBOT,
+    Solver,
+    Human,
 }
 
-fn select_opponent(input: &mut io::Lines<io::StdinLock<'_>>) -> Result<Opponent, Done> {
+impl Player {
+    fn name(&self) -> &'static str {
+        match self {
+            Player::MCTS => "mcts",
+            Player::HBOT => "hbot",
+            Player::Solver => "solver",
+            Player::Human => "human",
+        }
+    }
+}
+
+impl FromStr for Player {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Player, String> {
+        match s {
+            "mcts" => Ok(Player::MCTS),
+            "hbot" => Ok(Player::HBOT),
+            "solver" => Ok(Player::Solver),
+            "human" => Ok(Player::Human),
+            _ => Err(format!("unknown player: {}", s)),
+        }
+    }
+}
+
+fn select_opponent(input: &mut io::Lines<io::StdinLock<'_>>) -> Result<Player, Done> {
     let mut message: Option<String> = None;
 
-    println!("Opponents\n1: MCTS\n2: HBOT\n");
+    println!("Opponents\n1: MCTS\n2: HBOT\n3: Solver\n4: Human\n");
 
     loop {
         if let Some(msg) = message {
             println!("{}", msg);
         }
 
-        print!("Who's your opponent? [1,2]: ");
+        print!("Who's your opponent? [1,2,3,4]: ");
         io::stdout().flush().unwrap();
         
         let line = match input.next().transpose() {
@@ -169,8 +240,10 @@ fn select_opponent(input: &mut io::Lines<io::StdinLock<'_>>) -> Result<Opponent,
         };
 
         break match col {
-            1 => Ok(Opponent::MCTS),
-            2 => Ok(Opponent::HBOT),
+            1 => Ok(Player::MCTS),
+            2 => Ok(Player::HBOT),
+            3 => Ok(Player::Solver),
+            4 => Ok(Player::Human),
             _ => {
                 message = Some(format!("please select a valid opponent"));
                 continue;
@@ -179,51 +252,444 @@ fn select_opponent(input: &mut io::Lines<io::StdinLock<'_>>) -> Result<Opponent,
     }
 }
 
+const HELP: &str = "\
+usage: connect-4 [OPTIONS]
+       connect-4 export [--format dot|json] [--depth N] [--min-visits N] [--memory PATH]
+                        [--cols N] [--rows N] [--win N]
+       connect-4 replay PATH [--no-color]
+
+Plays connect four in the terminal.
+
+Board:
+    --cols N               columns of the board [default: 7]
+    --rows N               rows of the board [default: 6]
+    --win N                tokens in a line needed to win [default: 4]
+
+Players:
+    --first human|bot      who moves first [default: human]
+    --opponent NAME        mcts, hbot, solver or human; asked for when not given
+    --player1 NAME         who plays first, instead of --first and --opponent
+    --player2 NAME         who plays second [default: human]
+    --delay-ms N           pause after every move when only bots play [default: 500]
+
+Bot:
+    --think-ms N           time the mcts bot thinks per move [default: 1000, or
+                           unlimited with --iterations]
+    --iterations N         simulations the mcts bot runs per move
+    --seed N               seed of the bots' random numbers
+    --rave                 blend in all-moves-as-first statistics
+    --prior WEIGHT         seed moves with column scores of the given weight
+    --rollout POLICY       uniform, tactical or heuristic [default: uniform]
+    --ponder               keep thinking during the player's turn
+    --memory PATH          load the bot's memory from PATH and save it back on exit
+    --verbose              show the bot's analysis of its moves
+
+Game:
+    --save PATH            record the game to PATH on exit
+    --load PATH            continue the game recorded in PATH
+    --no-color             plain text output without colors or screen clearing
+    -h, --help             show this help
+";
+
+fn help() {
+    print!("{}", HELP);
+}
+
+// options of `start` and `export`, which are followed by a value
+const START_OPTIONS: &[&str] = &[
+    "--cols", "--rows", "--win", "--first", "--opponent", "--player1", "--player2", "--delay-ms",
+    "--think-ms", "--iterations", "--seed", "--prior", "--rollout", "--memory", "--save", "--load",
+];
+const START_SWITCHES: &[&str] = &["--rave", "--ponder", "--verbose", "--no-color"];
+const EXPORT_OPTIONS: &[&str] = &["--format", "--depth", "--min-visits", "--memory", "--cols", "--rows", "--win"];
+
+// fails on the first argument after the `skip` leading ones that isn't one of `options`
+// followed by a value, or one of `switches`; returns whether help was asked for, which
+// values of options never do
+fn check_args(skip: usize, options: &[&str], switches: &[&str]) -> Result<bool, String> {
+    let mut args = env::args().skip(1 + skip);
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(true)
+        } else if options.contains(&arg.as_str()) {
+            if args.next().is_none() {
+                return Err(format!("missing value for {}", arg))
+            }
+        } else if !switches.contains(&arg.as_str()) {
+            return Err(format!("unknown argument: {}, see --help", arg))
+        }
+    }
+
+    Ok(false)
+}
+
+fn flag_value(flag: &str) -> Option<String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next()
+        }
+    }
+
+    None
+}
+
+fn has_flag(flag: &str) -> bool {
+    env::args().skip(1).any(|arg| arg == flag)
+}
+
+// value of `flag` parsed, if it is given
+fn optional_flag<T: FromStr>(flag: &str) -> Result<Option<T>, String> {
+    match flag_value(flag) {
+        None => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(|_| format!("invalid value for {}: {}", flag, value)),
+    }
+}
+
+// value of `flag` parsed, or `default` when it is not given
+fn parsed_flag<T: FromStr>(flag: &str, default: T) -> Result<T, String> {
+    Ok(optional_flag(flag)?.unwrap_or(default))
+}
+
+// board given with `--cols`, `--rows` and `--win`, standard by default
+fn new_game() -> Result<Game, String> {
+    let cols = parsed_flag("--cols", 7)?;
+    let rows = parsed_flag("--rows", 6)?;
+    let win_len = parsed_flag("--win", 4)?;
+
+    Game::try_custom(cols, rows, win_len).map_err(|err| format!("invalid board: {}", err))
+}
+
+// search limits given with `--think-ms` and `--iterations`, along with how they read
+// in game records
+fn search_limits() -> Result<(SearchLimits, String), String> {
+    let iterations = optional_flag("--iterations")?;
+    let think_ms = match optional_flag("--think-ms")? {
+        None if iterations.is_none() => Some(1000),
+        think_ms => think_ms,
+    };
+
+    let mut limits = SearchLimits::new();
+    let mut settings = Vec::new();
+    if let Some(think_ms) = think_ms {
+        limits = limits.time(Duration::from_millis(think_ms));
+        settings.push(format!("think {}ms", think_ms));
+    }
+
+    if let Some(iterations) = iterations {
+        limits = limits.iterations(iterations);
+        settings.push(format!("{} iterations", iterations));
+    }
+
+    Ok((limits, settings.join(", ")))
+}
+
+// game recorded at `path`, with the moves played
+fn load_game(path: &str) -> Result<(Game, Vec<Option<f64>>), String> {
+    let record = Record::load(path).map_err(|err| format!("failed to load {}: {}", path, err))?;
+    let game = record.game().map_err(|err| format!("failed to replay {}: {}", path, err))?;
+
+    Ok((game, record.moves.iter().map(|m| m.value).collect()))
+}
+
+// values of the moves of `game`, after its last move was played
+fn note_value(values: &mut Vec<Option<f64>>, game: &Game, value: Option<f64>) {
+    values.resize(game.history().len() - 1, None);
+    values.push(value);
+}
+
+fn save_record(game: &Game, path: &Option<String>, players: &[Player; 2], settings: &Option<String>, values: &[Option<f64>]) {
+    let path = match path {
+        None => return,
+        Some(path) => path,
+    };
+
+    let mut record = Record::new(game, [String::from(players[0].name()), String::from(players[1].name())]);
+    record.settings = settings.clone();
+    record.date = Some(record::date(SystemTime::now()));
+    for (m, value) in record.moves.iter_mut().zip(values.iter()) {
+        m.value = *value;
+    }
+
+    if let Err(err) = record.save(path) {
+        println!("failed to save the game to {}: {}", path, err);
+    }
+}
+
+fn save_memory(mcts: &MCTS, path: &Option<String>) {
+    if let Some(path) = path {
+        if let Err(err) = mcts.save(path) {
+            println!("failed to save memory to {}: {}", path, err);
+        }
+    }
+}
+
+fn seat(token: Token) -> usize {
+    match token {
+        Token::Player1 => 0,
+        Token::Player2 => 1,
+    }
+}
+
 pub fn start() {
-    use board::Token::{Player1, Player2};
+    match check_args(0, START_OPTIONS, START_SWITCHES) {
+        Ok(false) => (),
+        Ok(true) => return help(),
+        Err(err) => {
+            println!("{}", err);
+            return
+        },
+    }
 
-    let mut mcts = MCTS::new();
-    let mut game = Game::new();
+    let (limits, mut mcts_settings) = match search_limits() {
+        Ok(limits) => limits,
+        Err(err) => {
+            println!("{}", err);
+            return
+        },
+    };
 
-    let stdin = io::stdin();
-    let mut lines = stdin.lock().lines();
+    let seed = match optional_flag::<u64>("--seed") {
+        Ok(seed) => seed,
+        Err(err) => {
+            println!("{}", err);
+            return
+        },
+    };
 
-    let opponent = select_opponent(&mut lines).unwrap();
+    let mut mcts = seed.map_or_else(MCTS::new, MCTS::with_seed);
+    if has_flag("--rave") {
+        mcts.use_rave();
+        mcts_settings.push_str(", rave");
+    }
 
-    let mut last_move: Option<(usize, usize)> = None;
-    while !game.over() {
-        match game.current_player() {
-            Player1 => match player_move(&mut game, &mut lines, |game: &Game| print_board(game, last_move)) {
+    if let Some(weight) = flag_value("--prior") {
+        match weight.parse() {
+            Ok(weight) => {
+                mcts.use_column_score_prior(weight);
+                mcts_settings.push_str(&format!(", prior {}", weight));
+            },
+            Err(_) => {
+                println!("invalid prior weight: {}", weight);
+                return
+            },
+        }
+    }
+
+    match flag_value("--rollout").as_deref() {
+        None | Some("uniform") => (),
+        Some("tactical") => mcts.set_tactical_rollout(),
+        Some("heuristic") => mcts.set_heuristic_rollout(0.1),
+        Some(other) => {
+            println!("unknown rollout policy: {}", other);
+            return
+        },
+    }
+
+    if let Some(rollout) = flag_value("--rollout") {
+        mcts_settings.push_str(&format!(", {} rollouts", rollout));
+    }
+
+    let human_first = match flag_value("--first").as_deref() {
+        None | Some("human") => true,
+        Some("bot") => false,
+        Some(other) => {
+            println!("invalid value for --first: {}", other);
+            return
+        },
+    };
+
+    let opponent = match optional_flag::<Player>("--opponent") {
+        Ok(opponent) => opponent,
+        Err(err) => {
+            println!("{}", err);
+            return
+        },
+    };
+
+    // both sides given with `--player1` and `--player2`, if either is
+    let seats = match (optional_flag::<Player>("--player1"), optional_flag::<Player>("--player2")) {
+        (Ok(None), Ok(None)) => None,
+        (Ok(first), Ok(second)) => Some([first.unwrap_or(Player::Human), second.unwrap_or(Player::Human)]),
+        (Err(err), _) | (_, Err(err)) => {
+            println!("{}", err);
+            return
+        },
+    };
+
+    let delay = match parsed_flag("--delay-ms", 500) {
+        Ok(delay) => Duration::from_millis(delay),
+        Err(err) => {
+            println!("{}", err);
+            return
+        },
+    };
+
+    // with `--load`, the game recorded in a file is picked up where it was left, and
+    // with `--save` it is recorded on exit
+    let loaded = match flag_value("--load") {
+        Some(path) => load_game(&path),
+        None => new_game().map(|game| (game, Vec::new())),
+    };
+
+    let (mut game, mut values) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            println!("{}", err);
+            return
+        },
+    };
+
+    let record_path = flag_value("--save");
+    let color = !has_flag("--no-color");
+
+    let mut rng = seed.map_or_else(Rng::from_entropy, Rng::new);
+    let mut solver = Solver::with_node_limit(1_000_000);
+
+    // path given with `--memory`, from which the bot is warm-started and to which its
+    // memory is written back on exit
+    let memory = flag_value("--memory");
+    if let Some(ref path) = memory {
+        if Path::new(path).exists() {
+            match mcts.load(path, &game) {
                 Err(err) => {
-                    println!("{}", err);
+                    println!("failed to load memory from {}: {}", path, err);
                     return
                 },
-                Ok(coord) => {
-                    last_move = Some(coord);
+                Ok(nodes) => println!("loaded {} positions from {}", nodes, path),
+            }
+        }
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    let players = match seats {
+        Some(players) => players,
+        None => {
+            let opponent = match opponent {
+                Some(opponent) => opponent,
+                None => match select_opponent(&mut lines) {
+                    Ok(opponent) => opponent,
+                    Err(_) => return,
                 },
-            },
-            Player2 => {
-                println!("{}", termion::clear::All);
-                match opponent {
-                    Opponent::MCTS => {
-                        print_board(&game, last_move);
-                        print!("thinking...");
-                        io::stdout().flush().unwrap();
-
-                        let results = mcts.think(&game, Duration::new(1, 0));
-
-                        println!("{}", termion::clear::All);
-                        println!("ran {} simulations; ({},{},{})", results[3], results[0], results[1], results[2]);
-                        last_move = Some(mcts_move(&mut game, &mcts));
+            };
+
+            if human_first {
+                [Player::Human, opponent]
+            } else {
+                [opponent, Player::Human]
+            }
+        },
+    };
+
+    let humans = players.iter().filter(|player| **player == Player::Human).count();
+    let has_mcts = players.contains(&Player::MCTS);
+
+    // settings of the bots that have any, named when there are several
+    let mut bot_settings = Vec::new();
+    for player in [Player::MCTS, Player::Solver].iter() {
+        if players.contains(player) {
+            let settings = match player {
+                Player::MCTS => mcts_settings.clone(),
+                _ => String::from("1000000 nodes"),
+            };
+
+            bot_settings.push((player.name(), settings));
+        }
+    }
+
+    let settings = match bot_settings.len() {
+        0 => None,
+        1 => Some(bot_settings[0].1.clone()),
+        _ => Some(bot_settings.iter().map(|(name, settings)| format!("{}: {}", name, settings)).collect::<Vec<_>>().join("; ")),
+    };
+
+    // the whole tree is kept when it is written back on exit, otherwise only the
+    // subtree of the moves actually played is
+    let reuse = memory.is_none() && has_mcts;
+    if reuse {
+        mcts.set_root(&game);
+    }
+
+    // with `--verbose`, the bot's analysis of every move is shown instead of win rates
+    let verbose = has_flag("--verbose");
+
+    // with `--ponder`, the bot keeps searching while waiting for the player's move
+    let ponder = has_flag("--ponder") && has_mcts && humans == 1;
+
+    // moves taken back at once, so that the same player is to move again
+    let undo_moves = if humans == 2 { 1 } else { 2 };
+
+    while !game.over() {
+        match players[seat(game.current_player())] {
+            Player::Human => {
+                let position = game.clone();
+                let mut read_move = || player_move(&mut game, &mut lines, undo_moves, color, |game: &Game| print_board(game, color));
+                let result = if ponder {
+                    mcts.ponder(&position, read_move).0
+                } else {
+                    read_move()
+                };
+
+                match result {
+                    Err(err) => {
+                        println!("{}", err);
+                        save_memory(&mcts, &memory);
+                        save_record(&game, &record_path, &players, &settings, &values);
+                        return
+                    },
+                    Ok(_) => {
+                        note_value(&mut values, &game, None);
+
+                        // the player may have taken moves back, which advancing the
+                        // root can't follow
+                        if reuse {
+                            mcts.set_root(&game);
+                        }
                     },
-                    Opponent::HBOT => {
-                        last_move = Some(hbot_move(&mut game));
-                    }
                 }
-            }
+            },
+            Player::MCTS => {
+                clear_screen(color);
+                print_board(&game, color);
+                print!("thinking...");
+                io::stdout().flush().unwrap();
+
+                let reused = mcts.visits(&game);
+                let report = mcts.think(&game, limits);
+
+                clear_screen(color);
+                println!("ran {} simulations ({} reused); ({},{},{})", report.iterations, reused, report.wins, report.losses, report.draws);
+                let (col, _) = mcts_move(&mut game, &mcts, &report, verbose);
+                let value = report.moves.iter().find(|m| m.col == col).map(|m| m.value);
+                note_value(&mut values, &game, value);
+                if reuse {
+                    mcts.advance(col).unwrap();
+                }
+            },
+            Player::HBOT => {
+                clear_screen(color);
+                hbot_move(&mut game, &mut rng);
+                note_value(&mut values, &game, None);
+            },
+            Player::Solver => {
+                clear_screen(color);
+                solver_move(&mut game, &mut solver);
+                note_value(&mut values, &game, None);
+            },
+        }
+
+        // when only bots play, every move is shown for a while
+        if humans == 0 && !game.over() {
+            print_board(&game, color);
+            thread::sleep(delay);
         }
     }
 
+    save_memory(&mcts, &memory);
+    save_record(&game, &record_path, &players, &settings, &values);
+
     let mut board = game.board();
     let winner = match game.winner() {
         Some((player, cells)) => {
@@ -236,15 +702,162 @@ pub fn start() {
         None => None,
     };
 
-    println!("{}", termion::clear::All);
+    clear_screen(color);
     print_board_top(board.cols());
-    println!("{}", board);
+    if color {
+        println!("{}", board);
+    } else {
+        println!("{:#}", board);
+    }
 
     match winner {
-        Some(player) => match player {
-            Player1 => println!("You win!"),
-            Player2 => println!("You lose!"),
+        Some(token) => match (players[seat(token)], players[seat(token.other())]) {
+            (Player::Human, Player::Human) => println!("Player {} wins!", seat(token) + 1),
+            (Player::Human, _) => println!("You win!"),
+            (_, Player::Human) => println!("You lose!"),
+            (winner, _) => println!("Player {} ({}) wins!", seat(token) + 1, winner.name()),
         },
         None => println!("It's a tie!"),
     }
 }
+
+/// `export [--format dot|json] [--depth N] [--min-visits N] [--memory PATH]`: prints
+/// the search tree of the opening position of the board given with `--cols`, `--rows`
+/// and `--win`, either loaded from a memory file or searched for a second.
+pub fn export() {
+    match check_args(1, EXPORT_OPTIONS, &[]) {
+        Ok(false) => (),
+        Ok(true) => return help(),
+        Err(err) => {
+            eprintln!("{}", err);
+            return
+        },
+    }
+
+    let options = (
+        parsed_flag("--format", Format::Dot),
+        parsed_flag("--depth", 3),
+        parsed_flag("--min-visits", 100),
+    );
+
+    let (format, depth, min_visits) = match options {
+        (Ok(format), Ok(depth), Ok(min_visits)) => (format, depth, min_visits),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+            eprintln!("{}", err);
+            return
+        },
+    };
+
+    let game = match new_game() {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("{}", err);
+            return
+        },
+    };
+
+    let mut mcts = MCTS::new();
+    match flag_value("--memory") {
+        Some(path) => if let Err(err) = mcts.load(&path, &game) {
+            eprintln!("failed to load memory from {}: {}", path, err);
+            return
+        },
+        None => {
+            mcts.think(&game, Duration::new(1, 0));
+        },
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if let Err(err) = mcts.tree(&game, depth, min_visits).write(format, &mut out) {
+        eprintln!("failed to write the tree: {}", err);
+    }
+}
+
+/// `replay PATH [--no-color]`: steps through a recorded game, showing the value and
+/// comment of every move.
+pub fn replay() {
+    let path = match env::args().nth(2) {
+        Some(ref path) if !path.starts_with('-') => path.clone(),
+        _ => {
+            println!("usage: replay PATH [--no-color]");
+            return
+        },
+    };
+
+    match check_args(2, &[], &["--no-color"]) {
+        Ok(false) => (),
+        Ok(true) => return help(),
+        Err(err) => {
+            println!("{}", err);
+            return
+        },
+    }
+
+    let color = !has_flag("--no-color");
+
+    let record = match Record::load(&path) {
+        Ok(record) => record,
+        Err(err) => {
+            println!("failed to load {}: {}", path, err);
+            return
+        },
+    };
+
+    let mut game = match record.game() {
+        Ok(game) => game,
+        Err(err) => {
+            println!("failed to replay {}: {}", path, err);
+            return
+        },
+    };
+
+    while game.undo().is_some() {}
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        clear_screen(color);
+        println!(
+            "{} vs {}{}, {}{}",
+            record.players[0],
+            record.players[1],
+            record.settings.as_ref().map_or(String::new(), |settings| format!(" ({})", settings)),
+            record.result,
+            record.date.as_ref().map_or(String::new(), |date| format!(" on {}", date)),
+        );
+
+        print_board(&game, color);
+
+        let ply = game.history().len();
+        if ply > 0 {
+            let m = &record.moves[ply - 1];
+            let mut line = format!("move {}/{}: {}", ply, record.moves.len(), m.col + 1);
+            if let Some(value) = m.value {
+                line.push_str(&format!(" ({:.3})", value));
+            }
+
+            if let Some(ref comment) = m.comment {
+                line.push_str(&format!(" {}", comment));
+            }
+
+            println!("{}", line);
+        }
+
+        print!("[enter] next, b back, q quit: ");
+        io::stdout().flush().unwrap();
+
+        match lines.next() {
+            None | Some(Err(_)) => return,
+            Some(Ok(line)) => match line.trim() {
+                "q" => return,
+                "b" => {
+                    game.undo();
+                },
+                _ => {
+                    game.redo();
+                },
+            },
+        }
+    }
+}
@@ -1,6 +1,14 @@
 #[cfg(not(target_arch = "wasm32"))]
 extern crate rand;
 
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn random() -> f64 {
     rand::random()
@@ -14,4 +22,77 @@ extern "C" {
 
     #[wasm_bindgen(js_namespace = Date)]
     fn now() -> usize;
+
+    #[wasm_bindgen(js_namespace = JSON, js_name = "parse")]
+    pub fn parse_json(text: &str) -> JsValue;
+}
+
+/// Seedable xorshift64* generator, so that a seeded bot plays the same moves on every
+/// target.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // scramble the seed with splitmix64 so that nearby seeds diverge and zero,
+        // which xorshift can't leave, never comes up
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    /// A generator seeded from the platform's random source.
+    pub fn from_entropy() -> Rng {
+        let high = (random() * (1u64 << 32) as f64) as u64;
+        let low = (random() * (1u64 << 32) as f64) as u64;
+        Rng::new(high << 32 | low)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A number in `[0, 1)`, like `random()`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number in `[0, n)`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize
+    }
+}
+
+/// Measures elapsed wall-clock time on native and wasm targets alike.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Stopwatch(Instant);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Stopwatch {
+    pub fn start() -> Stopwatch {
+        Stopwatch(Instant::now())
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// Measures elapsed wall-clock time on native and wasm targets alike.
+#[cfg(target_arch = "wasm32")]
+pub struct Stopwatch(usize);
+
+#[cfg(target_arch = "wasm32")]
+impl Stopwatch {
+    pub fn start() -> Stopwatch {
+        Stopwatch(now())
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis((now() - self.0) as u64)
+    }
 }
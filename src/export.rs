@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use game::Key;
+use mcts::Proof;
+use report::{json_proof, proof_name, MoveReport};
+
+#[derive(Debug)]
+pub struct UnknownFormatError;
+
+impl fmt::Display for UnknownFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown format, expected dot or json")
+    }
+}
+
+impl error::Error for UnknownFormatError {}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Format {
+    /// Graphviz DOT, with columns numbered from 1 as on the board.
+    Dot,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = UnknownFormatError;
+
+    fn from_str(s: &str) -> Result<Format, UnknownFormatError> {
+        match s {
+            "dot" => Ok(Format::Dot),
+            "json" => Ok(Format::Json),
+            _ => Err(UnknownFormatError),
+        }
+    }
+}
+
+/// A position of an exported search tree; see `MCTS::tree`.
+#[derive(Clone, Debug)]
+pub struct Tree {
+    pub key: Key,
+    pub visits: usize,
+    /// Proven value for the player to move.
+    pub proof: Option<Proof>,
+    pub edges: Vec<Edge>,
+}
+
+/// A move of an exported search tree, with the statistics of the player making it.
+#[derive(Clone, Debug)]
+pub struct Edge {
+    pub report: MoveReport,
+    pub child: Tree,
+}
+
+impl Tree {
+    pub fn write<W: io::Write>(&self, format: Format, w: &mut W) -> io::Result<()> {
+        match format {
+            Format::Dot => self.write_dot(w),
+            Format::Json => self.write_json(w),
+        }
+    }
+
+    /// Writes a Graphviz digraph where positions are labelled with their number of
+    /// visits and moves with their column, visits and value. Transpositions are drawn
+    /// once.
+    pub fn write_dot<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "digraph mcts {{")?;
+        writeln!(w, "    node [shape=box];")?;
+        writeln!(w, "    \"{:016x}\" [style=bold];", self.key)?;
+
+        let mut written = HashSet::new();
+        let mut stack = vec![self];
+        while let Some(tree) = stack.pop() {
+            if !written.insert(tree.key) {
+                continue
+            }
+
+            let proof = proof_name(tree.proof).map_or(String::new(), |name| format!("\\n{}", name));
+            writeln!(w, "    \"{:016x}\" [label=\"{}{}\"];", tree.key, tree.visits, proof)?;
+
+            for edge in tree.edges.iter() {
+                writeln!(
+                    w,
+                    "    \"{:016x}\" -> \"{:016x}\" [label=\"{} ({})\\n{:.3}\"];",
+                    tree.key,
+                    edge.child.key,
+                    edge.report.col + 1,
+                    edge.report.visits,
+                    edge.report.value,
+                )?;
+
+                stack.push(&edge.child);
+            }
+        }
+
+        writeln!(w, "}}")
+    }
+
+    /// Writes nested objects of the form `{"key", "visits", "proof", "edges"}`, where
+    /// keys are hexadecimal strings since they don't fit in a JavaScript number, and
+    /// edges are `{"move", "child"}` with the move as in `MoveReport::to_json`.
+    pub fn write_json<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, r#"{{"key":"{:016x}","visits":{},"proof":{},"edges":["#, self.key, self.visits, json_proof(self.proof))?;
+        for (i, edge) in self.edges.iter().enumerate() {
+            if i > 0 {
+                write!(w, ",")?;
+            }
+
+            write!(w, r#"{{"move":{},"child":"#, edge.report.to_json())?;
+            edge.child.write_json(w)?;
+            write!(w, "}}")?;
+        }
+
+        write!(w, "]}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> Tree {
+        let leaf = |key| Tree {
+            key,
+            visits: 1,
+            proof: None,
+            edges: Vec::new(),
+        };
+
+        Tree {
+            key: 1,
+            visits: 3,
+            proof: Some(Proof::Win),
+            edges: vec![
+                Edge { report: MoveReport::new(0, (1.0, 1), None), child: leaf(2) },
+                Edge { report: MoveReport::new(3, (2.0, 2), Some(Proof::Win)), child: leaf(2) },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_export_dot() {
+        let mut out = Vec::new();
+        tree().write(Format::Dot, &mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+
+        assert!(dot.starts_with("digraph mcts {\n"));
+        assert!(dot.contains("\"0000000000000001\" [label=\"3\\nwin\"];"));
+        assert!(dot.contains("\"0000000000000001\" -> \"0000000000000002\" [label=\"4 (2)\\n1.000\"];"));
+
+        // the transposition is only labelled once
+        assert_eq!(dot.matches("\n    \"0000000000000002\" [label").count(), 1);
+    }
+
+    #[test]
+    fn test_export_json() {
+        let mut out = Vec::new();
+        tree().write(Format::Json, &mut out).unwrap();
+        let json = String::from_utf8(out).unwrap();
+
+        assert!(json.starts_with(r#"{"key":"0000000000000001","visits":3,"proof":"win","edges":[{"move":{"col":0,"#));
+        assert!(json.ends_with(r#""child":{"key":"0000000000000002","visits":1,"proof":null,"edges":[]}}]}"#));
+    }
+
+    #[test]
+    fn test_export_format() {
+        assert_eq!("dot".parse::<Format>().unwrap(), Format::Dot);
+        assert_eq!("json".parse::<Format>().unwrap(), Format::Json);
+        assert!("svg".parse::<Format>().is_err());
+    }
+}
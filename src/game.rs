@@ -5,8 +5,13 @@ use std::error;
 use std::fmt;
 use std::cmp;
 use std::iter;
+use std::str::FromStr;
 
-use board::{Board, Token};
+use bitboard::Position;
+use board::Token;
+
+#[cfg(not(target_arch = "wasm32"))]
+use board::Board;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -22,17 +27,112 @@ impl fmt::Display for GameOverError {
 
 impl error::Error for GameOverError {}
 
+/// Largest number of columns or rows of a board.
+pub const MAX_SIZE: usize = 64;
+
+/// Why a board can't be set up with the given size and win length.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ConfigError {
+    /// The board has no columns or no rows.
+    Empty,
+    /// The board has more than `MAX_SIZE` columns or rows.
+    TooLarge,
+    /// Lines need at least two tokens.
+    WinLengthTooShort,
+    /// Lines don't fit both across and up the board.
+    WinLengthTooLong,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Empty => write!(f, "the board needs at least one column and one row"),
+            ConfigError::TooLarge => write!(f, "the board can't have more than {} columns or rows", MAX_SIZE),
+            ConfigError::WinLengthTooShort => write!(f, "the win length must be at least 2"),
+            ConfigError::WinLengthTooLong => write!(f, "the win length can't be more than the number of columns or rows"),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+/// Why a move list or a board diagram couldn't be read.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ParseError {
+    /// The move at this index of the list isn't a column of the board.
+    InvalidColumn(usize),
+    /// The move at this index of the list goes into a full column or comes after the
+    /// game is over.
+    IllegalMove(usize),
+    /// The diagram isn't made of rows, a side to move and a win length.
+    MissingField,
+    InvalidCell(char),
+    UnevenRows,
+    InvalidSideToMove,
+    InvalidWinLength,
+    Config(ConfigError),
+    /// A token sits above an empty cell.
+    FloatingToken,
+    /// The diagram can't come up in a game, given the side to move.
+    ImpossiblePosition,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidColumn(i) => write!(f, "move {} is not a column of the board", i + 1),
+            ParseError::IllegalMove(i) => write!(f, "move {} can't be played", i + 1),
+            ParseError::MissingField => write!(f, "expected rows, a side to move and a win length"),
+            ParseError::InvalidCell(c) => write!(f, "invalid cell: {}", c),
+            ParseError::UnevenRows => write!(f, "rows have different numbers of cells"),
+            ParseError::InvalidSideToMove => write!(f, "the side to move must be x or o"),
+            ParseError::InvalidWinLength => write!(f, "the win length must be a number"),
+            ParseError::Config(err) => write!(f, "{}", err),
+            ParseError::FloatingToken => write!(f, "a token is above an empty cell"),
+            ParseError::ImpossiblePosition => write!(f, "the position can't come up in a game"),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
 pub type State = Vec<u8>;
 
+/// Compact 64-bit Zobrist key of a position, maintained incrementally by `Game::drop`.
+pub type Key = u64;
+
+/// Returns the Zobrist value of `token` occupying the cell at `index`.
+///
+/// The values are derived from the index instead of a random table so that keys are
+/// stable across runs and board sizes.
+pub fn zobrist(index: usize, token: Token) -> Key {
+    let player = match token {
+        Token::Player1 => 1,
+        Token::Player2 => 2,
+    };
+
+    // splitmix64 finalizer
+    let mut z = (index as u64 * 2 + player).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Clone)]
 pub struct Game {
-    board: Board,
+    position: Position,
     win_len: usize,
+    key: Key,
+    mirror_key: Key,
 
     current_player: Token,
 
     winner: Option<(Token, Box<[(usize, usize)]>)>,
+
+    // columns played so far, and the ones taken back that can be redone, last first
+    history: Vec<usize>,
+    undone: Vec<usize>,
 }
 
 pub fn search_ranges<'a>(cols: usize, rows: usize, win_len: usize, col: usize, row: usize) -> Box<dyn iter::Iterator<Item = Box<dyn iter::Iterator<Item = (usize, usize)> + 'a>> + 'a> {
@@ -115,53 +215,145 @@ pub fn search_ranges<'a>(cols: usize, rows: usize, win_len: usize, col: usize, r
 }
 
 impl Game {
+    /// A board of `cols` by `rows` where lines of `win_length` tokens win.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration is invalid; see `try_custom`.
     pub fn custom(cols: usize, rows: usize, win_length: usize) -> Game {
-        Game {
-            board: Board::new(cols, rows),
+        match Game::try_custom(cols, rows, win_length) {
+            Ok(game) => game,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Like `custom`, but fails for boards without cells or larger than `MAX_SIZE`
+    /// either way, and for win lengths below 2 or longer than the board is wide or
+    /// high.
+    pub fn try_custom(cols: usize, rows: usize, win_length: usize) -> Result<Game, ConfigError> {
+        if cols == 0 || rows == 0 {
+            return Err(ConfigError::Empty)
+        }
+
+        if cols > MAX_SIZE || rows > MAX_SIZE {
+            return Err(ConfigError::TooLarge)
+        }
+
+        if win_length < 2 {
+            return Err(ConfigError::WinLengthTooShort)
+        }
+
+        if win_length > cols || win_length > rows {
+            return Err(ConfigError::WinLengthTooLong)
+        }
+
+        Ok(Game {
+            position: Position::new(cols, rows),
             win_len: win_length,
+            key: 0,
+            mirror_key: 0,
 
             current_player: Token::Player1,
 
             winner: None,
-        }
+
+            history: Vec::new(),
+            undone: Vec::new(),
+        })
     }
 
     pub fn new() -> Game {
         Game::custom(7, 6, 4)
     }
 
+    /// A standard game after the moves of `moves`; see `play_moves`.
+    pub fn from_moves(moves: &str) -> Result<Game, ParseError> {
+        let mut game = Game::new();
+        game.play_moves(moves)?;
+        Ok(game)
+    }
+
+    /// Plays a list of columns numbered from 1, either one digit per move as in
+    /// `"4453"` or separated by spaces or commas for boards wider than 9 columns.
+    /// Moves before the first one that can't be played stay on the board.
+    pub fn play_moves(&mut self, moves: &str) -> Result<(), ParseError> {
+        let moves: Vec<&str> = if moves.contains(|c: char| c.is_whitespace() || c == ',') {
+            moves.split(|c: char| c.is_whitespace() || c == ',').filter(|s| !s.is_empty()).collect()
+        } else {
+            moves.char_indices().map(|(i, c)| &moves[i..i + c.len_utf8()]).collect()
+        };
+
+        for (i, col) in moves.into_iter().enumerate() {
+            let col = match usize::from_str(col) {
+                Ok(col) if col >= 1 && col <= self.cols() => col - 1,
+                _ => return Err(ParseError::InvalidColumn(i)),
+            };
+
+            if self.drop(col).is_err() {
+                return Err(ParseError::IllegalMove(i))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The moves played so far in the notation of `play_moves`.
+    ///
+    /// Tokens of a diagram the game was parsed from are not moves, so they aren't
+    /// included.
+    pub fn to_moves(&self) -> String {
+        let separator = if self.cols() > 9 { " " } else { "" };
+        self.history.iter()
+            .map(|col| (col + 1).to_string())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
     pub fn win_len(&self) -> usize {
         self.win_len
     }
 
-    fn check_winner(&self, player: Token, col: usize, row: usize) -> Option<Box<[(usize, usize)]>> {
-        let ranges = search_ranges(self.cols(), self.rows(), self.win_len, col, row);
-        'outer: for range in ranges {
-            let v = range.collect::<Vec<_>>();
-            for (col, row) in v.clone() {
-                if !self.board.token_at(col, row)
-                    .and_then(|p| Some(p == player))
-                    .unwrap_or(false) {
-                    continue 'outer;
-                }
-            }
+    pub fn key(&self) -> Key {
+        self.key
+    }
 
-            return Some(v.into_boxed_slice())
-        }
+    /// Key of the position's mirror image, with the columns in reverse order.
+    pub fn mirror_key(&self) -> Key {
+        self.mirror_key
+    }
 
-        None
+    /// Whether a position and its mirror image are worth the same under the rules of
+    /// the game, which holds for every variant so far.
+    pub fn symmetric(&self) -> bool {
+        true
     }
 
+    /// Columns played so far, in order.
+    pub fn history(&self) -> &[usize] {
+        &self.history
+    }
+
+    /// Drops a token into `col` for the current player and returns the row it landed
+    /// in. Moves that were undone can no longer be redone afterwards.
     pub fn drop(&mut self, col: usize) -> Result<usize, Box<dyn error::Error>> {
+        let row = self.play(col)?;
+        self.undone.clear();
+
+        Ok(row)
+    }
+
+    fn play(&mut self, col: usize) -> Result<usize, Box<dyn error::Error>> {
         use board::Token::{Player1, Player2};
 
         if self.over() {
             return Err(Box::new(GameOverError))
         }
 
-        let row = self.board.drop(col, self.current_player)?;
+        let row = self.position.drop(col, self.current_player)?;
+        self.toggle_key(col, row, self.current_player);
+        self.history.push(col);
 
-        if let Some(cells) = self.check_winner(self.current_player, col, row) {
+        if let Some(cells) = self.position.winning_line(self.current_player, self.win_len, col, row) {
             self.winner = Some((self.current_player, cells));
         }
 
@@ -173,18 +365,47 @@ impl Game {
         Ok(row)
     }
 
+    // adds or removes `token` at `(col, row)` from the keys
+    fn toggle_key(&mut self, col: usize, row: usize, token: Token) {
+        self.key ^= zobrist(col * self.rows() + row, token);
+        self.mirror_key ^= zobrist((self.cols() - 1 - col) * self.rows() + row, token);
+    }
+
     pub fn valid_moves(&self) -> Vec<usize> {
-        (0..self.cols()).filter_map(|col| match self.board.token_at(col, self.rows()-1) {
-            None => Some(col),
-            Some(_) => None,
-        }).collect()
+        (0..self.cols()).filter(|col| self.position.height(*col) < self.rows()).collect()
+    }
+
+    /// Number of tokens in `col`, which is also the row the next one lands in.
+    pub fn height(&self, col: usize) -> usize {
+        self.position.height(col)
+    }
+
+    pub fn token_at(&self, col: usize, row: usize) -> Option<Token> {
+        self.position.token_at(col, row)
+    }
+
+    /// Whether dropping a `token` into `col` would complete a line, whoever's turn it is.
+    pub fn winning_move(&self, col: usize, token: Token) -> bool {
+        self.winning_cell(col, self.height(col), token)
+    }
+
+    /// Whether a `token` at `(col, row)` would complete a line, even if the cell can't be
+    /// played yet.
+    pub fn winning_cell(&self, col: usize, row: usize, token: Token) -> bool {
+        // lines are counted outwards from the cell, which doesn't have to be filled
+        row < self.rows() && self.position.winning_line(token, self.win_len, col, row).is_some()
+    }
+
+    /// The token of the winning line, if any; unlike `winner`, the same on every target.
+    pub fn winning_token(&self) -> Option<Token> {
+        self.winner.as_ref().map(|(token, _)| *token)
     }
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 impl Game {
     pub fn over(&self) -> bool {
-        self.winner != None || self.valid_moves().len() == 0
+        self.winner.is_some() || self.position.full()
     }
 
     pub fn current_player(&self) -> Token {
@@ -192,16 +413,43 @@ impl Game {
     }
 
     pub fn cols(&self) -> usize {
-        self.board.cols()
+        self.position.cols()
     }
 
     pub fn rows(&self) -> usize {
-        self.board.rows()
+        self.position.rows()
+    }
+
+    /// Number of tokens that have been dropped so far.
+    pub fn ply(&self) -> usize {
+        self.position.stones()
+    }
+
+    /// Takes back the last move and returns its column, if any move was played.
+    pub fn undo(&mut self) -> Option<usize> {
+        let col = self.history.pop()?;
+        let row = self.position.pop(col).unwrap();
+
+        // nobody could have won before the last move, since the game went on
+        let token = self.current_player.other();
+        self.toggle_key(col, row, token);
+        self.current_player = token;
+        self.winner = None;
+
+        self.undone.push(col);
+        Some(col)
+    }
+
+    /// Plays the last move taken back again and returns its column, if there is one.
+    pub fn redo(&mut self) -> Option<usize> {
+        let col = self.undone.pop()?;
+        self.play(col).unwrap();
+        Some(col)
     }
 
     pub fn state(&self) -> State {
         iproduct!(0..self.cols(), 0..self.rows())
-            .map(|(col, row)| match self.board.token_at(col, row) {
+            .map(|(col, row)| match self.position.token_at(col, row) {
                 None => 0,
                 Some(Token::Player1) => 1,
                 Some(Token::Player2) => 2,
@@ -217,7 +465,14 @@ impl Game {
     }
 
     pub fn board(&self) -> Board {
-        self.board.clone()
+        let mut board = Board::new(self.cols(), self.rows());
+        for (col, row) in iproduct!(0..self.cols(), 0..self.rows()) {
+            if let Some(token) = self.position.token_at(col, row) {
+                board.fill(col, row, token).unwrap();
+            }
+        }
+
+        board
     }
 }
 
@@ -229,14 +484,22 @@ impl Game {
         if cols == None && rows == None && win_length == None {
             Ok(Game::new())
         } else if let (Some(c), Some(r), Some(l)) = (cols, rows, win_length) {
-            Ok(Game::custom(c, r, l))
+            Game::try_custom(c, r, l).map_err(|err| format!("{}", err).into())
         } else if let (Some(c), Some(r), None) = (cols, rows, win_length) {
-            Ok(Game::custom(c, r, 4))
+            Game::try_custom(c, r, 4).map_err(|err| format!("{}", err).into())
         } else {
             Err("invalid arguments".into())
         }
     }
 
+    #[wasm_bindgen(js_name = "history")]
+    pub fn history_wasm(&self) -> Box<[JsValue]> {
+        self.history.iter()
+            .map(|col| JsValue::from(*col as u32))
+            .collect::<Vec<_>>()
+            .into_boxed_slice()
+    }
+
     #[wasm_bindgen(js_name = "valid_moves")]
     pub fn valid_moves_wasm(&self) -> Box<[JsValue]> {
         self.valid_moves().into_iter()
@@ -273,7 +536,7 @@ impl Game {
             .into_iter()
             .map(|col| (0..self.rows())
                 .into_iter()
-                .map(|row| match self.board.token_at(col, row) {
+                .map(|row| match self.position.token_at(col, row) {
                     Some(token) => Some(match token {
                         Token::Player1 => 0,
                         Token::Player2 => 1,
@@ -285,10 +548,151 @@ impl Game {
     }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+fn token_char(token: Token) -> char {
+    match token {
+        Token::Player1 => 'x',
+        Token::Player2 => 'o',
+    }
+}
+
+/// A diagram of the board followed by the side to move and the win length, such as
+/// `7/7/7/7/3o3/2oxx2 x 4`.
+///
+/// Rows go from the top of the board down and are separated by `/`; `x` and `o` are
+/// the tokens of the first and the second player, and numbers count empty cells.
 impl fmt::Display for Game {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.board)
+        let mut rows = Vec::new();
+        for row in (0..self.rows()).rev() {
+            let mut text = String::new();
+            let mut empty = 0;
+            for col in 0..self.cols() {
+                match self.position.token_at(col, row) {
+                    None => empty += 1,
+                    Some(token) => {
+                        if empty > 0 {
+                            text.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+
+                        text.push(token_char(token));
+                    },
+                }
+            }
+
+            if empty > 0 {
+                text.push_str(&empty.to_string());
+            }
+
+            rows.push(text);
+        }
+
+        write!(f, "{} {} {}", rows.join("/"), token_char(self.current_player), self.win_len)
+    }
+}
+
+/// Reads the notation written by `Display`. The game has no history, so the tokens of
+/// the diagram can't be undone.
+impl FromStr for Game {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Game, ParseError> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 3 {
+            return Err(ParseError::MissingField)
+        }
+
+        let mut rows = Vec::new();
+        for text in fields[0].split('/') {
+            let mut cells = Vec::new();
+            let mut empty = 0;
+            for c in text.chars() {
+                if let Some(digit) = c.to_digit(10) {
+                    // checked before the row is filled, so long runs can't overflow
+                    empty = empty * 10 + digit as usize;
+                    if cells.len() + empty > MAX_SIZE {
+                        return Err(ParseError::Config(ConfigError::TooLarge))
+                    }
+
+                    continue;
+                }
+
+                cells.extend(iter::repeat_n(None, empty));
+                empty = 0;
+
+                cells.push(Some(match c {
+                    'x' => Token::Player1,
+                    'o' => Token::Player2,
+                    _ => return Err(ParseError::InvalidCell(c)),
+                }));
+
+                if cells.len() > MAX_SIZE {
+                    return Err(ParseError::Config(ConfigError::TooLarge))
+                }
+            }
+
+            cells.extend(iter::repeat_n(None, empty));
+            rows.push(cells);
+        }
+
+        // bottom row first
+        rows.reverse();
+
+        let cols = rows[0].len();
+        if rows.iter().any(|cells| cells.len() != cols) {
+            return Err(ParseError::UnevenRows)
+        }
+
+        let side = match fields[1] {
+            "x" => Token::Player1,
+            "o" => Token::Player2,
+            _ => return Err(ParseError::InvalidSideToMove),
+        };
+
+        let win_len = usize::from_str(fields[2]).map_err(|_| ParseError::InvalidWinLength)?;
+        let mut game = Game::try_custom(cols, rows.len(), win_len).map_err(ParseError::Config)?;
+
+        let mut counts = [0, 0];
+        for col in 0..cols {
+            for (row, cells) in rows.iter().enumerate() {
+                let token = match cells[col] {
+                    None => continue,
+                    Some(token) => token,
+                };
+
+                if game.position.drop(col, token).unwrap() != row {
+                    return Err(ParseError::FloatingToken)
+                }
+
+                game.toggle_key(col, row, token);
+                counts[if token == Token::Player1 { 0 } else { 1 }] += 1;
+            }
+        }
+
+        let expected = if counts[0] == counts[1] { Token::Player1 } else { Token::Player2 };
+        if side != expected || counts[0] < counts[1] || counts[0] > counts[1] + 1 {
+            return Err(ParseError::ImpossiblePosition)
+        }
+
+        game.current_player = side;
+
+        for (col, row) in iproduct!(0..cols, 0..game.rows()) {
+            let token = match game.position.token_at(col, row) {
+                None => continue,
+                Some(token) => token,
+            };
+
+            if let Some(cells) = game.position.winning_line(token, win_len, col, row) {
+                // only the player who moved last can have won
+                if token == side {
+                    return Err(ParseError::ImpossiblePosition)
+                }
+
+                game.winner = Some((token, cells));
+            }
+        }
+
+        Ok(game)
     }
 }
 
@@ -300,11 +704,12 @@ mod tests {
     use self::rand::Rng;
 
     fn print_search_ranges(cols: usize, rows: usize, wins: usize, col: usize, row: usize) {
-        let mut game = Game::custom(cols, rows, wins);
+        let game = Game::custom(cols, rows, wins);
 
-        game.board.highlight(col, row);
+        let mut highlighted = game.board();
+        highlighted.highlight(col, row);
         for range in search_ranges(game.cols(), game.rows(), game.win_len, col, row) {
-            let mut board = game.board.clone();
+            let mut board = highlighted.clone();
             for (col, row) in range {
                 print!("({},{}) ", col, row);
                 board.fill(col, row, Token::Player1).unwrap();
@@ -366,4 +771,167 @@ mod tests {
 
         // println!("{}", board)
     }
+
+    #[test]
+    fn test_game_key_transpositions() {
+        let mut a = Game::new();
+        let mut b = Game::new();
+        for col in [3, 2, 4, 2].iter() {
+            a.drop(*col).unwrap();
+        }
+        for col in [4, 2, 3, 2].iter() {
+            b.drop(*col).unwrap();
+        }
+
+        assert_eq!(a.state(), b.state());
+        assert_eq!(a.key(), b.key());
+
+        b.drop(0).unwrap();
+        assert_ne!(a.key(), b.key());
+    }
+
+    #[test]
+    fn test_game_try_custom() {
+        assert!(Game::try_custom(7, 6, 4).is_ok());
+        assert!(Game::try_custom(2, 2, 2).is_ok());
+        assert!(Game::try_custom(MAX_SIZE, MAX_SIZE, MAX_SIZE).is_ok());
+
+        assert_eq!(Game::try_custom(0, 6, 4).err(), Some(ConfigError::Empty));
+        assert_eq!(Game::try_custom(7, 0, 4).err(), Some(ConfigError::Empty));
+        assert_eq!(Game::try_custom(MAX_SIZE + 1, 6, 4).err(), Some(ConfigError::TooLarge));
+        assert_eq!(Game::try_custom(7, 200, 4).err(), Some(ConfigError::TooLarge));
+        assert_eq!(Game::try_custom(7, 6, 1).err(), Some(ConfigError::WinLengthTooShort));
+        assert_eq!(Game::try_custom(7, 6, 7).err(), Some(ConfigError::WinLengthTooLong));
+        assert_eq!(Game::try_custom(3, 6, 4).err(), Some(ConfigError::WinLengthTooLong));
+    }
+
+    #[test]
+    fn test_game_undo_redo() {
+        let mut game = Game::new();
+        assert_eq!(game.undo(), None);
+
+        let mut positions = vec![(game.key(), game.mirror_key())];
+        for col in [3, 4, 3, 4, 3, 4].iter() {
+            game.drop(*col).unwrap();
+            positions.push((game.key(), game.mirror_key()));
+        }
+
+        game.drop(3).unwrap();
+        assert!(game.winner().is_some());
+        assert_eq!(game.history(), &[3, 4, 3, 4, 3, 4, 3]);
+
+        assert_eq!(game.undo(), Some(3));
+        assert!(game.winner().is_none());
+        assert!(game.current_player() == Token::Player1);
+        assert_eq!((game.key(), game.mirror_key()), positions[6]);
+
+        assert_eq!(game.undo(), Some(4));
+        assert!(game.current_player() == Token::Player2);
+        assert!(game.token_at(4, 2).is_none());
+        assert_eq!(game.ply(), 5);
+        assert_eq!((game.key(), game.mirror_key()), positions[5]);
+
+        assert_eq!(game.redo(), Some(4));
+        assert_eq!(game.redo(), Some(3));
+        assert_eq!(game.redo(), None);
+        assert!(game.winner().is_some());
+
+        // a new move drops the moves that could be redone
+        while game.undo().is_some() {}
+        assert_eq!((game.key(), game.mirror_key()), positions[0]);
+        game.drop(0).unwrap();
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.history(), &[0]);
+    }
+
+    #[test]
+    fn test_game_moves() {
+        let game = Game::from_moves("4453").unwrap();
+        assert_eq!(game.history(), &[3, 3, 4, 2]);
+        assert_eq!(game.to_moves(), "4453");
+
+        let mut wide = Game::custom(12, 6, 4);
+        wide.play_moves("12, 1 10").unwrap();
+        assert_eq!(wide.history(), &[11, 0, 9]);
+        assert_eq!(wide.to_moves(), "12 1 10");
+
+        assert_eq!(Game::from_moves("48").err(), Some(ParseError::InvalidColumn(1)));
+        assert_eq!(Game::from_moves("40").err(), Some(ParseError::InvalidColumn(1)));
+        assert_eq!(Game::from_moves("4a").err(), Some(ParseError::InvalidColumn(1)));
+        assert_eq!(Game::from_moves("1111111").err(), Some(ParseError::IllegalMove(6)));
+        assert_eq!(Game::from_moves("12121214").err(), Some(ParseError::IllegalMove(7)));
+    }
+
+    #[test]
+    fn test_game_diagram() {
+        let game = Game::from_moves("4453").unwrap();
+        let diagram = "7/7/7/7/3o3/2oxx2 x 4";
+        assert_eq!(game.to_string(), diagram);
+
+        let parsed = diagram.parse::<Game>().unwrap();
+        assert_eq!(parsed.to_string(), diagram);
+        assert_eq!((parsed.key(), parsed.mirror_key()), (game.key(), game.mirror_key()));
+        assert_eq!(parsed.ply(), 4);
+        assert!(parsed.history().is_empty());
+
+        let won = Game::from_moves("1212121").unwrap();
+        let parsed = won.to_string().parse::<Game>().unwrap();
+        assert!(parsed.over());
+        assert!(parsed.winner().map(|(token, _)| token) == Some(Token::Player1));
+
+        assert_eq!("10/10 x 2".parse::<Game>().unwrap().cols(), 10);
+
+        assert_eq!("7/7 x".parse::<Game>().err(), Some(ParseError::MissingField));
+        assert_eq!("7/3y3 x 4".parse::<Game>().err(), Some(ParseError::InvalidCell('y')));
+        assert_eq!("7/6 x 4".parse::<Game>().err(), Some(ParseError::UnevenRows));
+        assert_eq!("7/7 - 4".parse::<Game>().err(), Some(ParseError::InvalidSideToMove));
+        assert_eq!("7/7 x four".parse::<Game>().err(), Some(ParseError::InvalidWinLength));
+        assert_eq!("7/7 x 4".parse::<Game>().err(), Some(ParseError::Config(ConfigError::WinLengthTooLong)));
+        assert_eq!("99999999999999999999/7 x 4".parse::<Game>().err(), Some(ParseError::Config(ConfigError::TooLarge)));
+        assert_eq!("1000000000000/7 x 4".parse::<Game>().err(), Some(ParseError::Config(ConfigError::TooLarge)));
+        assert_eq!(format!("{}/7 x 4", "x".repeat(MAX_SIZE + 1)).parse::<Game>().err(), Some(ParseError::Config(ConfigError::TooLarge)));
+        assert_eq!("x6/7 o 2".parse::<Game>().err(), Some(ParseError::FloatingToken));
+        assert_eq!("7/x6 x 2".parse::<Game>().err(), Some(ParseError::ImpossiblePosition));
+        assert_eq!("7/oo5 x 2".parse::<Game>().err(), Some(ParseError::ImpossiblePosition));
+        assert_eq!("7/oxxo3 x 2".parse::<Game>().err(), Some(ParseError::ImpossiblePosition));
+    }
+
+    #[test]
+    fn test_game_mirror_key() {
+        let mut a = Game::new();
+        let mut b = Game::new();
+        for col in [0, 3, 1].iter() {
+            a.drop(*col).unwrap();
+            b.drop(6 - *col).unwrap();
+        }
+
+        assert_eq!(a.key(), b.mirror_key());
+        assert_eq!(a.mirror_key(), b.key());
+        assert_ne!(a.key(), b.key());
+    }
+
+    #[test]
+    fn test_game_winner_matches_search_ranges() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..200 {
+            let cols: usize = rng.gen_range(3, 12);
+            let rows: usize = rng.gen_range(3, 10);
+            let wins: usize = rng.gen_range(2, cmp::min(cols, rows) + 1);
+
+            let mut game = Game::custom(cols, rows, wins);
+            while !game.over() {
+                let moves = game.valid_moves();
+                let player = game.current_player();
+                let col = moves[rng.gen_range(0, moves.len())];
+                let row = game.drop(col).unwrap();
+
+                let board = game.board();
+                let expected = search_ranges(cols, rows, wins, col, row)
+                    .any(|mut range| range.all(|(col, row)| board.token_at(col, row) == Some(player)));
+
+                assert_eq!(expected, game.winner().is_some());
+            }
+        }
+    }
 }
\ No newline at end of file
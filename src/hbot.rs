@@ -1,10 +1,10 @@
-use board::{Board, Token};
+use board::Token;
 use game::{Game, search_ranges};
-use common::random;
+use common::Rng;
 use std::iter;
 
-fn range_score<'a>(board: &Board, token: Token, range: Box<dyn iter::Iterator<Item = (usize, usize)> + 'a>) -> usize {
-    range.fold(0, |sum, (col, row)| sum + match board.token_at(col, row) {
+fn range_score<'a>(game: &Game, token: Token, range: Box<dyn iter::Iterator<Item = (usize, usize)> + 'a>) -> usize {
+    range.fold(0, |sum, (col, row)| sum + match game.token_at(col, row) {
         Some(t) => if t == token {
             1
         } else {
@@ -14,25 +14,21 @@ fn range_score<'a>(board: &Board, token: Token, range: Box<dyn iter::Iterator<It
     })
 }
 
-fn calculate_column_score(board: &Board, token: Token, win_len: usize, col: usize) -> usize {
-    match board.top(col) {
-        Some(row) => search_ranges(board.cols(), board.rows(), win_len, col, row)
-            .map(|range| range_score(board, token, range))
-            .fold(0, |best, score| if score > best {
-                score
-            } else {
-                score
-            }),
-        None => 0,
+/// Score of the cell that dropping a `token` into `col` would fill, or 0 for a full
+/// column.
+pub fn calculate_column_score(game: &Game, token: Token, col: usize) -> usize {
+    match game.height(col) {
+        row if row < game.rows() => search_ranges(game.cols(), game.rows(), game.win_len(), col, row)
+            .map(|range| range_score(game, token, range))
+            .max()
+            .unwrap_or(0),
+        _ => 0,
     }
 }
 
-pub fn next_move(game: &Game) -> usize {
-    let board = game.board();
-
-    let column_scores = (0..game.cols())
-        .map(|col| calculate_column_score(&board, game.current_player(), game.win_len(), col))
-        .enumerate()
+pub fn next_move(game: &Game, rng: &mut Rng) -> usize {
+    let column_scores = game.valid_moves().into_iter()
+        .map(|col| (col, calculate_column_score(game, game.current_player(), col)))
         .collect::<Vec<_>>();
 
     let best_score = column_scores
@@ -53,10 +49,26 @@ pub fn next_move(game: &Game) -> usize {
         .collect::<Vec<_>>();
 
     if best_columns.len() == 0 {
-        (random() * board.cols() as f64) as usize
+        rng.below(game.cols())
     } else if best_columns.len() == 1 {
         best_columns[0]
     } else {
-        best_columns[(random() * best_columns.len() as f64) as usize]
+        best_columns[rng.below(best_columns.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_score_takes_best_range() {
+        // three in a column, the first range searched
+        let game = Game::from_moves("12121").unwrap();
+        assert_eq!(calculate_column_score(&game, Token::Player1, 0), 3);
+
+        let mut full = Game::custom(4, 2, 2);
+        full.play_moves("11").unwrap();
+        assert_eq!(calculate_column_score(&full, Token::Player1, 0), 0);
     }
-}
\ No newline at end of file
+}
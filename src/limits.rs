@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+/// Budget of a search; it stops as soon as any of the limits is reached.
+///
+/// At least one of the iteration, node or time limits should be set, otherwise the
+/// search may never end.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct SearchLimits {
+    iterations: Option<usize>,
+    nodes: Option<usize>,
+    time: Option<Duration>,
+    stop_when_decided: bool,
+}
+
+impl SearchLimits {
+    pub fn new() -> SearchLimits {
+        SearchLimits::default()
+    }
+
+    /// Stops after running `iterations` simulations.
+    pub fn iterations(mut self, iterations: usize) -> SearchLimits {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    /// Stops once the search has added `nodes` positions to the tree, not counting the
+    /// ones it started with.
+    pub fn nodes(mut self, nodes: usize) -> SearchLimits {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    /// Stops after `time` has elapsed.
+    pub fn time(mut self, time: Duration) -> SearchLimits {
+        self.time = Some(time);
+        self
+    }
+
+    /// Stops early once the most visited move can no longer be overtaken within the
+    /// remaining iterations or time.
+    pub fn stop_when_decided(mut self) -> SearchLimits {
+        self.stop_when_decided = true;
+        self
+    }
+
+    pub fn stops_when_decided(&self) -> bool {
+        self.stop_when_decided
+    }
+
+    /// Whether a search that has run `iterations` simulations and added `nodes` positions
+    /// in `elapsed` time has reached any of the limits.
+    pub fn exhausted(&self, iterations: usize, nodes: usize, elapsed: Duration) -> bool {
+        self.iterations.is_some_and(|max| iterations >= max)
+            || self.nodes.is_some_and(|max| nodes >= max)
+            || self.time.is_some_and(|max| elapsed >= max)
+    }
+
+    /// Estimates how many more iterations the search may run, extrapolating the time
+    /// limit from the speed so far.
+    pub fn remaining(&self, iterations: usize, elapsed: Duration) -> Option<usize> {
+        let by_iterations = self.iterations.map(|max| max.saturating_sub(iterations));
+        let by_time = self.time.and_then(|max| {
+            if elapsed.as_secs_f64() == 0.0 {
+                return None
+            }
+
+            let rate = iterations as f64 / elapsed.as_secs_f64();
+            Some((rate * max.saturating_sub(elapsed).as_secs_f64()).ceil() as usize)
+        });
+
+        match (by_iterations, by_time) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+
+    /// Divides the iteration budget between `parts` searchers running side by side,
+    /// returning the limits of the `i`th one.
+    pub fn split(&self, parts: usize, i: usize) -> SearchLimits {
+        let mut limits = *self;
+        if let Some(iterations) = self.iterations {
+            limits.iterations = Some(iterations / parts + if i < iterations % parts { 1 } else { 0 });
+        }
+
+        limits
+    }
+}
+
+impl From<Duration> for SearchLimits {
+    fn from(time: Duration) -> SearchLimits {
+        SearchLimits::new().time(time)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl SearchLimits {
+    #[wasm_bindgen(constructor)]
+    pub fn ctor(iterations: Option<usize>, nodes: Option<usize>, millis: Option<usize>, stop_when_decided: Option<bool>) -> SearchLimits {
+        SearchLimits {
+            iterations,
+            nodes,
+            time: millis.map(|millis| Duration::from_millis(millis as u64)),
+            stop_when_decided: stop_when_decided.unwrap_or(false),
+        }
+    }
+}
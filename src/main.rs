@@ -10,8 +10,15 @@ extern crate termion;
 
 pub mod game;
 pub mod mcts;
+pub mod policy;
+pub mod limits;
+pub mod report;
+pub mod export;
+pub mod record;
 pub mod hbot;
+pub mod solver;
 pub mod board;
+pub mod bitboard;
 pub mod common;
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -19,5 +26,9 @@ mod cli;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    cli::start()
+    match std::env::args().nth(1).as_deref() {
+        Some("export") => cli::export(),
+        Some("replay") => cli::replay(),
+        _ => cli::start(),
+    }
 }
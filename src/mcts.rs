@@ -1,142 +1,1224 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::io;
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[cfg(not(target_arch = "wasm32"))]
-use std::time::{Duration, SystemTime};
+use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
 
-use game::{Game, State};
-use common::random;
+use board::Token;
+use common::{Rng, Stopwatch};
+use export::{Edge, Tree};
+use game::{Game, Key, State};
+use limits::SearchLimits;
+use report::{MoveReport, SearchReport};
+use policy::{bias, blend, winrate, BetaSchedule, ColumnScore, Evaluator, Heuristic, RolloutPolicy, SelectionPolicy, Tactical, Ucb1, UniformRandom, WeightedRandom};
 
 #[cfg(target_arch = "wasm32")]
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = Date)]
-    fn now() -> usize;
+use wasm_bindgen::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use common::parse_json;
+
+/// Zobrist keys are already uniformly distributed, so they are used as their own hash.
+#[derive(Default)]
+pub struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(*byte);
+        }
+    }
+
+    fn write_u64(&mut self, key: u64) {
+        self.0 = key;
+    }
 }
 
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen::prelude::*;
+#[derive(Debug)]
+pub struct InvalidFileError;
 
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-pub struct MCTS {
-    memory: HashMap<State, Vec<(f64, usize)>>,
+impl fmt::Display for InvalidFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "not a memory file")
+    }
+}
+
+impl error::Error for InvalidFileError {}
+
+#[derive(Debug)]
+pub struct UnsupportedVersionError(pub u8);
+
+impl fmt::Display for UnsupportedVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unsupported memory file version {}", self.0)
+    }
+}
+
+impl error::Error for UnsupportedVersionError {}
+
+#[derive(Debug)]
+pub struct NoRootError;
+
+impl fmt::Display for NoRootError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no position to advance from")
+    }
 }
 
-fn random_weighted(weights: Vec<f64>) -> usize {
-    let n = weights.len();
-    if n == 0 {
-        panic!("no values")
+impl error::Error for NoRootError {}
+
+#[derive(Debug)]
+pub struct MismatchedBoardError;
+
+impl fmt::Display for MismatchedBoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "memory file is for a different board")
     }
+}
+
+impl error::Error for MismatchedBoardError {}
+
+const MAGIC: &[u8; 4] = b"C4MC";
+const VERSION: u8 = 2;
+
+const WIN: f64 = 1.0;
+const DRAW: f64 = 0.5;
+const LOSS: f64 = 0.0;
+
+type Memory<V> = HashMap<Key, V, BuildHasherDefault<KeyHasher>>;
+type KeySet = HashSet<Key, BuildHasherDefault<KeyHasher>>;
+
+// exploration constant of UCB1 when blended with RAVE statistics
+const RAVE_EXPLORATION: f64 = 0.2;
+
+// simulations run between checks for the end of pondering
+#[cfg(not(target_arch = "wasm32"))]
+const PONDER_SLICE: usize = 256;
+
+// columns, rows and win length of the board the memory belongs to
+type Shape = (usize, usize, usize);
+
+fn shape(game: &Game) -> Shape {
+    (game.cols(), game.rows(), game.win_len())
+}
+
+fn write_u64<W: io::Write>(w: &mut W, value: u64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn read_u64<R: io::Read>(r: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_u8<R: io::Read>(r: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    r.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// How `think` spreads the search over several threads.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Parallelism {
+    /// Independent searchers whose statistics are merged once the time is up.
+    Root,
+    /// Searchers sharing one tree, steered apart from each other with virtual losses.
+    Tree,
+}
 
-    let sum = weights.iter().fold(0.0, |sum, w| sum + w);
-    if sum == 0.0 {
-        return (random() * n as f64) as usize
+fn outcome(player: Token, winner: Option<Token>) -> (usize, usize, usize) {
+    match winner {
+        None => (0, 0, 1),
+        Some(token) if token == player => (1, 0, 0),
+        Some(_) => (0, 1, 0),
     }
+}
+
+/// Game-theoretic value established by the search, for the player making a move or
+/// the player to move in a position.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Proof {
+    Win,
+    Loss,
+    Draw,
+}
 
-    let value = random();
-    let mut progress = 0.0;
-    for (i, weight) in weights.iter().enumerate() {
-        let normalized = weight / sum;
-        if value < progress + normalized {
-            return i
+impl Proof {
+    fn invert(self) -> Proof {
+        match self {
+            Proof::Win => Proof::Loss,
+            Proof::Loss => Proof::Win,
+            Proof::Draw => Proof::Draw,
         }
+    }
 
-        progress += normalized;
+    /// The winner of a game where `player` is to move in a position with this value.
+    fn winner(self, player: Token) -> Option<Token> {
+        match self {
+            Proof::Win => Some(player),
+            Proof::Loss => Some(player.other()),
+            Proof::Draw => None,
+        }
     }
+}
 
-    unreachable!()
+fn proof_to_byte(proof: Option<Proof>) -> u8 {
+    match proof {
+        None => 0,
+        Some(Proof::Win) => 1,
+        Some(Proof::Loss) => 2,
+        Some(Proof::Draw) => 3,
+    }
 }
 
-fn winrate((score, games): (f64, usize)) -> f64 {
-    if games == 0 {
-        0.5
-    } else {
-        score / games as f64
+fn proof_from_byte(byte: u8) -> Result<Option<Proof>, InvalidFileError> {
+    match byte {
+        0 => Ok(None),
+        1 => Ok(Some(Proof::Win)),
+        2 => Ok(Some(Proof::Loss)),
+        3 => Ok(Some(Proof::Draw)),
+        _ => Err(InvalidFileError),
     }
 }
 
+/// Mirrors a state column by column.
+fn mirror_state(state: State, rows: usize) -> State {
+    state.chunks(rows).rev().flatten().cloned().collect()
+}
+
+// where a position is kept in memory: under its own key, or under the key of its mirror
+// image with the records of the columns in reverse order
+#[derive(Copy, Clone)]
+struct Slot {
+    key: Key,
+    mirrored: bool,
+    cols: usize,
+}
+
+impl Slot {
+    /// Maps a column of the position to the one recorded in memory and back.
+    fn col(&self, col: usize) -> usize {
+        if self.mirrored {
+            self.cols - 1 - col
+        } else {
+            col
+        }
+    }
+}
+
+// a move made while descending the tree, with columns as recorded in memory
+struct Step {
+    slot: Slot,
+    col: usize,
+    player: Token,
+    moves: Vec<usize>,
+}
+
+struct Node {
+    // (score, games) of each column, from the perspective of the player to move
+    stats: Vec<(f64, usize)>,
+
+    // all-moves-as-first (score, games) of each column, only recorded with RAVE
+    amaf: Vec<(f64, usize)>,
+
+    // priors of each column, only set when the bot has an evaluator
+    priors: Vec<f64>,
+
+    // proven values of each column and of the position as a whole
+    proofs: Vec<Option<Proof>>,
+    proof: Option<Proof>,
+
+    // full state of the position, only kept when collision checking is enabled
+    state: Option<State>,
+
+    // number of tokens on the board, kept in saved memories
+    ply: usize,
+}
+
+impl Node {
+    /// Credits `value` to the all-moves-as-first records of the columns where `player`
+    /// dropped the first token in `moves`, which are given as played, so that each of
+    /// them still stands for the cell it would fill at this position.
+    fn record_amaf(&mut self, moves: &[(Token, usize)], player: Token, value: f64, slot: Slot) {
+        if self.amaf.is_empty() {
+            self.amaf = vec![(0.0, 0); self.stats.len()];
+        }
+
+        let mut seen = vec![false; self.amaf.len()];
+        for &(token, col) in moves {
+            if seen[col] {
+                continue
+            }
+
+            seen[col] = true;
+            if token == player {
+                let record = &mut self.amaf[slot.col(col)];
+                record.0 += value;
+                record.1 += 1;
+            }
+        }
+    }
+
+    /// Applies the MCTS-Solver rules: a position is won if any move wins, and lost or
+    /// drawn once every move is proven and none of them wins.
+    fn resolve(&self, moves: &[usize]) -> Option<Proof> {
+        if moves.iter().any(|col| self.proofs[*col] == Some(Proof::Win)) {
+            Some(Proof::Win)
+        } else if moves.iter().all(|col| self.proofs[*col].is_some()) {
+            if moves.iter().any(|col| self.proofs[*col] == Some(Proof::Draw)) {
+                Some(Proof::Draw)
+            } else {
+                Some(Proof::Loss)
+            }
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct MCTS {
+    memory: Memory<Node>,
+    shape: Option<Shape>,
+
+    // position the memory is rooted at; everything kept is reachable from it
+    root: Option<Game>,
+
+    check_collisions: bool,
+    symmetry: bool,
+    rave: Option<BetaSchedule>,
+    prior: Option<(Arc<dyn Evaluator>, f64)>,
+    selection: Arc<dyn SelectionPolicy>,
+    rollout: Arc<dyn RolloutPolicy>,
+    rng: Rng,
+
+    max_nodes: Option<usize>,
+    max_bytes: Option<usize>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    threads: usize,
+    #[cfg(not(target_arch = "wasm32"))]
+    parallelism: Parallelism,
+}
+
 impl MCTS {
-    fn update(&mut self, score: f64, moves: Vec<(State, usize)>) {
-        // the distance to a leaf is inversely proportional to the score
-        // i.e. the closer to a winning or losing move, the greater the effect on
-        // the next choice of move
-        for (i, (state, m)) in moves.iter().rev().enumerate() {
-            let record = self.memory.get_mut(state).unwrap().get_mut(*m).unwrap();
-            record.0 += 0.5 + score * 0.5 / (i + 1) as f64;
-            record.1 += 1;
+    /// A position and its mirror image share a node when the game is symmetric, kept
+    /// under the lower of their keys.
+    fn slot(&self, game: &Game) -> Slot {
+        let mirrored = self.symmetry && game.symmetric() && game.mirror_key() < game.key();
+        Slot {
+            key: if mirrored { game.mirror_key() } else { game.key() },
+            mirrored,
+            cols: game.cols(),
+        }
+    }
+
+    /// State of `game` as recorded in its slot.
+    fn slot_state(game: &Game, slot: Slot) -> State {
+        if slot.mirrored {
+            mirror_state(game.state(), game.rows())
+        } else {
+            game.state()
+        }
+    }
+
+    fn node(&self, game: &Game) -> Option<&Node> {
+        if self.shape != Some(shape(game)) {
+            return None
         }
+
+        let slot = self.slot(game);
+        let node = self.memory.get(&slot.key)?;
+        match node.state {
+            Some(ref state) if *state != MCTS::slot_state(game, slot) => None,
+            _ => Some(node),
+        }
+    }
+
+    /// Approximate number of bytes taken by a node of `game`'s board in memory.
+    fn node_size(&self, game: &Game) -> usize {
+        // the extra byte accounts for the hash map's control byte
+        let mut size = mem::size_of::<(Key, Node)>() + 1
+            + game.cols() * (mem::size_of::<(f64, usize)>() + mem::size_of::<Option<Proof>>());
+        if self.check_collisions {
+            size += game.cols() * game.rows() * mem::size_of::<u8>();
+        }
+
+        if self.rave.is_some() {
+            size += game.cols() * mem::size_of::<(f64, usize)>();
+        }
+
+        if self.prior.is_some() {
+            size += game.cols() * mem::size_of::<f64>();
+        }
+
+        size
+    }
+
+    /// Maximum number of nodes allowed by the configured limits.
+    fn capacity(&self, game: &Game) -> Option<usize> {
+        let by_bytes = self.max_bytes.map(|bytes| bytes / self.node_size(game));
+        match (self.max_nodes, by_bytes) {
+            (Some(nodes), Some(bytes)) => Some(nodes.min(bytes)),
+            (nodes, None) => nodes,
+            (None, bytes) => bytes,
+        }
+    }
+
+    fn expand(&mut self, game: &Game) {
+        if self.node(game).is_some() {
+            return
+        }
+
+        // once the budget is spent the tree stops growing, but rollouts still go on
+        // from its leaves
+        if self.capacity(game).is_some_and(|capacity| self.memory.len() >= capacity) {
+            return
+        }
+
+        let slot = self.slot(game);
+        let state = if self.check_collisions {
+            Some(MCTS::slot_state(game, slot))
+        } else {
+            None
+        };
+
+        let priors = match self.prior {
+            Some((ref evaluator, _)) => {
+                let priors = evaluator.evaluate(game);
+                (0..game.cols()).map(|col| priors[slot.col(col)]).collect()
+            },
+            None => Vec::new(),
+        };
+
+        self.memory.insert(slot.key, Node {
+            stats: vec![(0.0, 0); game.cols()],
+            amaf: if self.rave.is_some() { vec![(0.0, 0); game.cols()] } else { Vec::new() },
+            priors,
+            proofs: vec![None; game.cols()],
+            proof: None,
+            state,
+            ply: game.ply(),
+        });
     }
 
-    pub fn move_weights(&self, state: &State, moves: &Vec<usize>) -> Vec<f64> {
-        let records = &self.memory[state];
+    /// Win rates of `moves`, or 1, 0 and 0.5 for moves proven to win, lose or draw.
+    pub fn move_weights(&self, game: &Game, moves: &[usize]) -> Vec<f64> {
+        let node = self.node(game);
+        let slot = self.slot(game);
 
         moves.iter()
-            .map(|col| winrate(records[*col]))
+            .map(|col| slot.col(*col))
+            .map(|col| match node.and_then(|node| node.proofs[col]) {
+                Some(Proof::Win) => WIN,
+                Some(Proof::Loss) => LOSS,
+                Some(Proof::Draw) => DRAW,
+                None => winrate(node.map_or((0.0, 0), |node| node.stats[col])),
+            })
             .collect::<Vec<_>>()
     }
 
-    fn pick_move(&self, state: &State, choices: &Vec<usize>) -> usize {
-        choices[random_weighted(self.move_weights(state, choices))]
+    /// Proven value of `col` at `game`, if any.
+    pub fn proof(&self, game: &Game, col: usize) -> Option<Proof> {
+        self.node(game)?.proofs[self.slot(game).col(col)]
     }
 
-    pub fn simulate(&mut self, mut game: Game) -> (usize, usize, usize) {
-        let mut my_moves: Vec<(State, usize)> = Vec::new();
-        let mut their_moves: Vec<(State, usize)> = Vec::new();
+    /// The move to play at `game`: a proven win if there is one, otherwise the highest
+    /// win rate among the moves that are not proven to lose, if any.
+    pub fn best_move(&self, game: &Game) -> Option<usize> {
+        let moves = game.valid_moves();
+        let weights = self.move_weights(game, &moves);
+        let proofs = moves.iter().map(|col| self.proof(game, *col)).collect::<Vec<_>>();
+
+        if let Some(i) = proofs.iter().position(|proof| *proof == Some(Proof::Win)) {
+            return Some(moves[i])
+        }
 
-        let cols = game.cols(); // cache this
+        let all_lost = proofs.iter().all(|proof| *proof == Some(Proof::Loss));
+        (0..moves.len())
+            .filter(|i| all_lost || proofs[*i] != Some(Proof::Loss))
+            .fold(None, |best: Option<usize>, i| match best {
+                Some(best) if weights[best] >= weights[i] => Some(best),
+                _ => Some(i),
+            })
+            .map(|i| moves[i])
+    }
 
-        // assume current player is positive
-        let mut i = 0;
+    /// The line of play expected from `game`: best moves for as long as they have been
+    /// simulated or proven.
+    pub fn principal_variation(&self, game: &Game) -> Vec<usize> {
+        let mut game = game.clone();
+        let mut line = Vec::new();
         while !game.over() {
-            // selection
-            let state = game.state();
-            if !self.memory.contains_key(&state) {
-                // expansion
-                self.memory.insert(state.clone(), vec![(0.0,0); cols]);
+            let (node, col) = match (self.node(&game), self.best_move(&game)) {
+                (Some(node), Some(col)) => (node, col),
+                _ => break,
+            };
+
+            let col_slot = self.slot(&game).col(col);
+            if node.stats[col_slot].1 == 0 && node.proofs[col_slot].is_none() {
+                break
             }
 
-            let col = self.pick_move(&state, &game.valid_moves());
+            line.push(col);
+            game.drop(col).unwrap();
+        }
 
-            // keep track of each players' moves
-            if i % 2 == 0 {
-                &mut my_moves
-            } else {
-                &mut their_moves
-            }.push((state, col));
+        line
+    }
+
+    /// The part of the tree below `game` made of moves simulated at least `min_visits`
+    /// times, down to `depth` moves deep.
+    pub fn tree(&self, game: &Game, depth: usize, min_visits: usize) -> Tree {
+        let node = self.node(game);
+        let mut tree = Tree {
+            key: game.key(),
+            visits: self.visits(game),
+            proof: node.and_then(|node| node.proof),
+            edges: Vec::new(),
+        };
+
+        let node = match node {
+            Some(node) if depth > 0 => node,
+            _ => return tree,
+        };
+
+        let slot = self.slot(game);
+        for col in game.valid_moves() {
+            let stats = node.stats[slot.col(col)];
+            if stats.1 == 0 || stats.1 < min_visits {
+                continue
+            }
+
+            let mut child = game.clone();
+            child.drop(col).unwrap();
+            tree.edges.push(Edge {
+                report: MoveReport::new(col, stats, node.proofs[slot.col(col)]),
+                child: self.tree(&child, depth - 1, min_visits),
+            });
+        }
+
+        tree
+    }
+
+    /// Everything known about the legal moves of `game`, along with the counters of a
+    /// search from it.
+    fn report(&self, game: &Game, results: [usize; 4], time: Duration) -> SearchReport {
+        let node = self.node(game);
+        let slot = self.slot(game);
+
+        SearchReport {
+            moves: game.valid_moves().into_iter()
+                .map(|col| match node {
+                    Some(node) => MoveReport::new(col, node.stats[slot.col(col)], node.proofs[slot.col(col)]),
+                    None => MoveReport::new(col, (0.0, 0), None),
+                })
+                .collect(),
+            best_move: self.best_move(game),
+            principal_variation: self.principal_variation(game),
+            iterations: results[3],
+            wins: results[0],
+            losses: results[1],
+            draws: results[2],
+            nodes: self.memory.len(),
+            time,
+        }
+    }
+
+    fn pick_move(&mut self, key: Key, moves: &[usize]) -> usize {
+        let node = &self.memory[&key];
 
+        // moves proven to lose are not worth exploring while there are others
+        let open;
+        let choices = if moves.iter().any(|col| node.proofs[*col] == Some(Proof::Loss)) {
+            open = moves.iter()
+                .cloned()
+                .filter(|col| node.proofs[*col] != Some(Proof::Loss))
+                .collect::<Vec<_>>();
+
+            // every move losing can only mean the position is not resolved yet
+            if open.is_empty() { moves } else { &open[..] }
+        } else {
+            moves
+        };
+
+        let mut adjusted = match self.rave {
+            Some(schedule) if !node.amaf.is_empty() => Some(blend(&node.stats, &node.amaf, schedule)),
+            _ => None,
+        };
+
+        if let Some((_, weight)) = self.prior {
+            if !node.priors.is_empty() {
+                // moves that have not been played yet are tried in order of their priors
+                let unvisited = choices.iter()
+                    .cloned()
+                    .filter(|col| node.stats[*col].1 == 0)
+                    .collect::<Vec<_>>();
+                if !unvisited.is_empty() {
+                    let best = unvisited.iter().map(|col| node.priors[*col]).fold(f64::NEG_INFINITY, f64::max);
+                    let best = unvisited.into_iter().filter(|col| node.priors[*col] == best).collect::<Vec<_>>();
+                    return best[self.rng.below(best.len())]
+                }
+
+                adjusted = Some(bias(adjusted.as_deref().unwrap_or(&node.stats), &node.priors, weight));
+            }
+        }
+
+        let stats = adjusted.as_deref().unwrap_or(&node.stats);
+        choices[self.selection.select(stats, choices, &mut self.rng)]
+    }
+
+    pub fn set_selection<P: SelectionPolicy + 'static>(&mut self, policy: P) {
+        self.selection = Arc::new(policy);
+    }
+
+    pub fn set_rollout<P: RolloutPolicy + 'static>(&mut self, policy: P) {
+        self.rollout = Arc::new(policy);
+    }
+
+    /// Enables Rapid Action Value Estimation: every move of a simulation is credited to
+    /// the positions before it where the same player could have played it, and these
+    /// all-moves-as-first statistics are blended into selection according to
+    /// `schedule`. `None` disables it.
+    ///
+    /// RAVE is best used with much less exploration than plain UCT; see `use_rave`.
+    pub fn set_rave(&mut self, schedule: Option<BetaSchedule>) {
+        self.rave = schedule;
+    }
+
+    /// Seeds new positions with the priors of `evaluator`: unplayed moves are tried in
+    /// order of their priors, and played ones get a progressive bias of
+    /// `weight * prior / (games + 1)` added to their win rate during selection.
+    pub fn set_prior<E: Evaluator + 'static>(&mut self, evaluator: E, weight: f64) {
+        self.prior = Some((Arc::new(evaluator), weight));
+    }
+
+    /// Walks down the tree with the selection policy until reaching a position that
+    /// has not been expanded yet, whose value is proven, or where the game ends.
+    fn select(&mut self, game: &mut Game, path: &mut Vec<Step>) {
+        while !game.over() {
+            match self.node(game) {
+                Some(node) if node.proof.is_none() => (),
+                _ => break,
+            }
+
+            let slot = self.slot(game);
+            let moves = game.valid_moves().iter().map(|col| slot.col(*col)).collect::<Vec<_>>();
+            let col = self.pick_move(slot.key, &moves);
+            path.push(Step {
+                slot,
+                col,
+                player: game.current_player(),
+                moves,
+            });
+
+            game.drop(slot.col(col)).unwrap();
+        }
+    }
+
+    /// Selection followed by expansion. When the outcome is known without a rollout,
+    /// returns the winner along with the proven value of the last move of the path.
+    fn descend(&mut self, game: &mut Game) -> (Vec<Step>, Option<(Option<Token>, Proof)>) {
+        let mut path = Vec::new();
+        self.select(game, &mut path);
+
+        if game.over() {
+            let winner = game.winning_token();
+            let proof = if winner.is_some() { Proof::Win } else { Proof::Draw };
+            return (path, Some((winner, proof)))
+        }
+
+        if let Some(proof) = self.node(game).and_then(|node| node.proof) {
+            return (path, Some((proof.winner(game.current_player()), proof.invert())))
+        }
+
+        self.expand(game);
+        (path, None)
+    }
+
+    /// Plays the game out with the rollout policy without recording anything in the
+    /// tree, and returns the moves played.
+    fn rollout(policy: &dyn RolloutPolicy, game: &mut Game, rng: &mut Rng) -> Vec<(Token, usize)> {
+        let mut moves = Vec::new();
+        while !game.over() {
+            let col = policy.play(game, rng);
+            moves.push((game.current_player(), col));
             game.drop(col).unwrap();
+        }
 
-            i += 1;
-        } // simulation ends on the loser's turn
+        moves
+    }
 
-        // back propagation
-        let (my_score, their_score, results) = if game.winner() == None { // tie
-            (0.0, 0.0, (0,0,1))
-        } else if i % 2 == 0 { // lost
-            (-1.0, 1.0, (0,1,0))
-        } else { // won
-            (1.0, -1.0, (1,0,0))
+    /// Credits the result of a simulation to every move along `path`, and propagates
+    /// the proven value of its last move upwards as far as it goes. Moves that had a
+    /// virtual loss applied already have their game counted. With RAVE, the moves of the
+    /// path and of the `rollout` that followed are also credited as all moves as first.
+    fn backpropagate(&mut self, path: Vec<Step>, rollout: &[(Token, usize)], winner: Option<Token>, mut proof: Option<Proof>, virtual_loss: bool) {
+        let played = if self.rave.is_some() {
+            path.iter()
+                .map(|step| (step.player, step.slot.col(step.col)))
+                .chain(rollout.iter().cloned())
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
         };
 
-        self.update(my_score as f64, my_moves);
-        self.update(their_score as f64, their_moves);
+        let rave = self.rave.is_some();
+        for (i, step) in path.into_iter().enumerate().rev() {
+            let value = match winner {
+                None => DRAW,
+                Some(token) if token == step.player => WIN,
+                Some(_) => LOSS,
+            };
 
-        results
+            let node = self.memory.get_mut(&step.slot.key).unwrap();
+            node.stats[step.col].0 += value;
+            if !virtual_loss {
+                node.stats[step.col].1 += 1;
+            }
+
+            if rave {
+                node.record_amaf(&played[i..], step.player, value, step.slot);
+            }
+
+            if proof.is_some() {
+                node.proofs[step.col] = proof;
+                node.proof = node.resolve(&step.moves);
+            }
+
+            // the move into this position is proven only if the position is
+            proof = node.proof.map(Proof::invert);
+        }
     }
-}
 
-#[cfg(not(target_arch = "wasm32"))]
-impl MCTS {
-    pub fn think(&mut self, game: &Game, duration: Duration) -> [usize; 4] {
-        let now = SystemTime::now();
-        let mut results = [0 as usize; 4];
-        while now.elapsed().unwrap() < duration {
+    /// Ties the memory to `game`'s board, forgetting everything learnt on other boards.
+    fn bind(&mut self, game: &Game) {
+        if self.shape != Some(shape(game)) {
+            self.memory.clear();
+            self.shape = Some(shape(game));
+            self.root = None;
+        }
+    }
+
+    /// Keys of every position in memory that can be reached from `root`.
+    fn reachable(&self, root: &Game) -> KeySet {
+        let mut seen = KeySet::default();
+        let mut stack = vec![root.clone()];
+        while let Some(game) = stack.pop() {
+            let key = self.slot(&game).key;
+            if game.over() || seen.contains(&key) || self.node(&game).is_none() {
+                continue
+            }
+
+            seen.insert(key);
+            for col in game.valid_moves() {
+                let mut child = game.clone();
+                child.drop(col).unwrap();
+                stack.push(child);
+            }
+        }
+
+        seen
+    }
+
+    pub fn root(&self) -> Option<&Game> {
+        self.root.as_ref()
+    }
+
+    /// Plays `col` from the root, keeping only the subtree of that move, and returns the
+    /// number of simulations carried over.
+    pub fn advance(&mut self, col: usize) -> Result<usize, Box<dyn error::Error>> {
+        let mut game = self.root.clone().ok_or(NoRootError)?;
+        game.drop(col)?;
+        Ok(self.set_root(&game))
+    }
+
+    /// Moves the root to `game` when searching a position other than the current root,
+    /// keeping the rest of the memory unless it is limited.
+    fn follow(&mut self, game: &Game) {
+        self.bind(game);
+
+        let rooted = self.root.as_ref().is_some_and(|root| root.key() == game.key());
+        if !rooted {
+            if self.limited() {
+                self.prune_to(game);
+            }
+
+            self.root = Some(game.clone());
+        }
+    }
+
+    fn limited(&self) -> bool {
+        self.max_nodes.is_some() || self.max_bytes.is_some()
+    }
+
+    /// Writes the memory in a compact little-endian binary format:
+    ///
+    /// ```text
+    /// "C4MC" version:u8 cols:u64 rows:u64 win_len:u64 nodes:u64
+    /// nodes * (key:u64 ply:u64 proof:u8 cols * (score:f64 games:u64 proof:u8)
+    ///          has_state:u8 [state:cols*rows bytes])
+    /// ```
+    ///
+    /// where proofs are 0 when unknown and 1, 2 and 3 for a win, loss and draw. Version
+    /// 1 files, which have no proofs, can still be read. RAVE statistics and priors are
+    /// not saved.
+    pub fn write<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let (cols, rows, win_len) = self.shape.unwrap_or((0, 0, 0));
+
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        for value in [cols, rows, win_len, self.memory.len()].iter() {
+            write_u64(w, *value as u64)?;
+        }
+
+        for (key, node) in self.memory.iter() {
+            write_u64(w, *key)?;
+            write_u64(w, node.ply as u64)?;
+            w.write_all(&[proof_to_byte(node.proof)])?;
+            for ((score, games), proof) in node.stats.iter().zip(node.proofs.iter()) {
+                write_u64(w, score.to_bits())?;
+                write_u64(w, *games as u64)?;
+                w.write_all(&[proof_to_byte(*proof)])?;
+            }
+
+            match node.state {
+                Some(ref state) => {
+                    w.write_all(&[1])?;
+                    w.write_all(state)?;
+                },
+                None => w.write_all(&[0])?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the memory with one written by `write`, provided that it was learnt on
+    /// the same board as `game`. Returns the number of positions read.
+    pub fn read<R: io::Read>(&mut self, r: &mut R, game: &Game) -> Result<usize, Box<dyn error::Error>> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Box::new(InvalidFileError))
+        }
+
+        let version = read_u8(r)?;
+        if version != 1 && version != VERSION {
+            return Err(Box::new(UnsupportedVersionError(version)))
+        }
+
+        let file_shape = (read_u64(r)? as usize, read_u64(r)? as usize, read_u64(r)? as usize);
+        if file_shape != shape(game) {
+            return Err(Box::new(MismatchedBoardError))
+        }
+
+        let (cols, rows, _) = file_shape;
+        let count = read_u64(r)? as usize;
+
+        let mut memory = Memory::default();
+        for _ in 0..count {
+            let key = read_u64(r)?;
+            let ply = read_u64(r)? as usize;
+            let proof = if version > 1 {
+                proof_from_byte(read_u8(r)?)?
+            } else {
+                None
+            };
+
+            let mut stats = Vec::with_capacity(cols);
+            let mut proofs = Vec::with_capacity(cols);
+            for _ in 0..cols {
+                stats.push((f64::from_bits(read_u64(r)?), read_u64(r)? as usize));
+                proofs.push(if version > 1 {
+                    proof_from_byte(read_u8(r)?)?
+                } else {
+                    None
+                });
+            }
+
+            let state = match read_u8(r)? {
+                0 => None,
+                1 => {
+                    let mut state = vec![0; cols * rows];
+                    r.read_exact(&mut state)?;
+                    Some(state)
+                },
+                _ => return Err(Box::new(InvalidFileError)),
+            };
+
+            memory.insert(key, Node {
+                stats,
+                amaf: Vec::new(),
+                priors: Vec::new(),
+                proofs,
+                proof,
+                state,
+                ply,
+            });
+        }
+
+        self.memory = memory;
+        self.shape = Some(file_shape);
+
+        // the loaded memory may not hold the old root at all
+        self.root = None;
+
+        Ok(count)
+    }
+
+    /// Whether the most visited move at `game` stays ahead of all the others even if
+    /// they get every one of the `remaining` simulations.
+    fn decided(&self, game: &Game, remaining: usize) -> bool {
+        let node = match self.node(game) {
+            None => return false,
+            Some(node) if node.proof.is_some() => return true,
+            Some(node) => node,
+        };
+
+        let slot = self.slot(game);
+        let mut visits = game.valid_moves().iter()
+            .map(|col| node.stats[slot.col(*col)].1)
+            .collect::<Vec<_>>();
+        visits.sort_unstable_by(|a, b| b.cmp(a));
+
+        visits.len() < 2 || visits[0] > visits[1] + remaining
+    }
+
+    /// `nodes` is the size of the memory when the search started, so that positions kept
+    /// from earlier searches don't count against the node limit.
+    fn should_stop(&self, game: &Game, limits: &SearchLimits, iterations: usize, nodes: usize, stopwatch: &Stopwatch) -> bool {
+        let elapsed = stopwatch.elapsed();
+        if limits.exhausted(iterations, self.memory.len().saturating_sub(nodes), elapsed) {
+            return true
+        }
+
+        // checking is relatively expensive, so only do it every so often
+        limits.stops_when_decided()
+            && iterations.is_multiple_of(64)
+            && limits.remaining(iterations, elapsed).is_some_and(|remaining| self.decided(game, remaining))
+    }
+
+    fn search(&mut self, game: &Game, limits: &SearchLimits) -> [usize; 4] {
+        let stopwatch = Stopwatch::start();
+        let nodes = self.memory.len();
+        let mut results = [0; 4];
+        while !self.should_stop(game, limits, results[3], nodes, &stopwatch) {
             let (wins, losses, ties) = self.simulate(game.to_owned());
             results[0] += wins;
             results[1] += losses;
             results[2] += ties;
             results[3] += 1;
         }
-        
+
+        results
+    }
+
+    pub fn simulate(&mut self, mut game: Game) -> (usize, usize, usize) {
+        self.bind(&game);
+
+        let player = game.current_player();
+
+        let (path, known) = self.descend(&mut game);
+        let (winner, proof, moves) = match known {
+            Some((winner, proof)) => (winner, Some(proof), Vec::new()),
+            None => {
+                let moves = MCTS::rollout(&*self.rollout, &mut game, &mut self.rng);
+                (game.winning_token(), None, moves)
+            },
+        };
+
+        self.backpropagate(path, &moves, winner, proof, false);
+
+        outcome(player, winner)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MCTS {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = io::BufWriter::new(File::create(path)?);
+        self.write(&mut file)?;
+        io::Write::flush(&mut file)
+    }
+
+    /// Loads a memory file saved for `game`'s board; see `read`.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P, game: &Game) -> Result<usize, Box<dyn error::Error>> {
+        self.read(&mut io::BufReader::new(File::open(path)?), game)
+    }
+
+    /// Runs searches in `threads` threads when thinking; a single thread searches
+    /// without any synchronisation.
+    pub fn set_threads(&mut self, threads: usize, parallelism: Parallelism) {
+        self.threads = threads.max(1);
+        self.parallelism = parallelism;
+    }
+
+    /// Searches from `game` until `limits`, or a plain `Duration`, are exhausted and
+    /// reports on the moves of `game`.
+    pub fn think<L: Into<SearchLimits>>(&mut self, game: &Game, limits: L) -> SearchReport {
+        let limits = limits.into();
+        let stopwatch = Stopwatch::start();
+        self.follow(game);
+        let results = match self.parallelism {
+            _ if self.threads == 1 => self.search(game, &limits),
+            Parallelism::Root => self.search_root_parallel(game, &limits),
+            Parallelism::Tree => self.search_tree_parallel(game, &limits),
+        };
+
+        self.report(game, results, stopwatch.elapsed())
+    }
+
+    /// Keeps searching from `game` on a background thread while `wait` runs, e.g. while
+    /// the opponent is choosing a move, and returns its result along with the number of
+    /// simulations run in the meantime.
+    ///
+    /// Searching stops early once the outcome of `game` is proven.
+    pub fn ponder<T, F: FnOnce() -> T>(&mut self, game: &Game, wait: F) -> (T, usize) {
+        let stop = AtomicBool::new(false);
+        let stop = &stop;
+
+        thread::scope(|scope| {
+            let handle = scope.spawn(move || {
+                let mut simulations = 0;
+                while !stop.load(Ordering::Relaxed) {
+                    match self.think_slice(game, PONDER_SLICE) {
+                        0 => break,
+                        n => simulations += n,
+                    }
+                }
+
+                simulations
+            });
+
+            let result = wait();
+            stop.store(true, Ordering::Relaxed);
+
+            (result, handle.join().unwrap())
+        })
+    }
+
+    /// An empty searcher with the same settings, seeded from this one.
+    fn fork(&mut self) -> MCTS {
+        MCTS {
+            memory: Memory::default(),
+            shape: self.shape,
+            root: None,
+            check_collisions: self.check_collisions,
+            symmetry: self.symmetry,
+            rave: self.rave,
+            prior: self.prior.clone(),
+            selection: self.selection.clone(),
+            rollout: self.rollout.clone(),
+            rng: Rng::new(self.rng.next_u64()),
+
+            max_nodes: self.max_nodes,
+            max_bytes: self.max_bytes,
+
+            threads: 1,
+            parallelism: self.parallelism,
+        }
+    }
+
+    /// Adds the statistics of `other` to this memory.
+    fn merge(&mut self, other: MCTS, game: &Game) {
+        let capacity = self.capacity(game);
+        for (key, node) in other.memory {
+            if let Some(existing) = self.memory.get_mut(&key) {
+                if existing.state.is_none() || node.state.is_none() || existing.state == node.state {
+                    for (record, (score, games)) in existing.stats.iter_mut().zip(node.stats) {
+                        record.0 += score;
+                        record.1 += games;
+                    }
+
+                    if existing.priors.is_empty() {
+                        existing.priors = node.priors;
+                    }
+
+                    if existing.amaf.is_empty() {
+                        existing.amaf = node.amaf;
+                    } else {
+                        for (record, (score, games)) in existing.amaf.iter_mut().zip(node.amaf) {
+                            record.0 += score;
+                            record.1 += games;
+                        }
+                    }
+
+                    for (proof, other) in existing.proofs.iter_mut().zip(node.proofs) {
+                        *proof = proof.or(other);
+                    }
+
+                    existing.proof = existing.proof.or(node.proof);
+                }
+            } else if capacity.is_none_or(|capacity| self.memory.len() < capacity) {
+                self.memory.insert(key, node);
+            }
+        }
+    }
+
+    /// Applies the MCTS-Solver rules again to every position reachable from `game`,
+    /// children first, since merging can prove all the moves of a position without
+    /// proving the position itself. Returns the proof of `game`.
+    fn resolve_from(&mut self, game: &Game, seen: &mut KeySet) -> Option<Proof> {
+        let slot = self.slot(game);
+        match self.node(game) {
+            None => return None,
+            Some(node) if node.proof.is_some() || seen.contains(&slot.key) => return node.proof,
+            _ => (),
+        }
+
+        seen.insert(slot.key);
+
+        let moves = game.valid_moves().iter().map(|col| slot.col(*col)).collect::<Vec<_>>();
+        let mut proofs = Vec::new();
+        for col in moves.iter() {
+            let mut child = game.clone();
+            child.drop(slot.col(*col)).unwrap();
+            if !child.over() {
+                proofs.push((*col, self.resolve_from(&child, seen).map(Proof::invert)));
+            }
+        }
+
+        let node = self.memory.get_mut(&slot.key).unwrap();
+        for (col, proof) in proofs {
+            if proof.is_some() {
+                node.proofs[col] = proof;
+            }
+        }
+
+        node.proof = node.resolve(&moves);
+        node.proof
+    }
+
+    /// Every searcher gets its share of the iterations, but checks the node limit
+    /// against its own tree only.
+    fn search_root_parallel(&mut self, game: &Game, limits: &SearchLimits) -> [usize; 4] {
+        let threads = self.threads;
+        let forks = (1..threads)
+            .map(|i| (self.fork(), limits.split(threads, i)))
+            .collect::<Vec<_>>();
+
+        let (mut results, forks) = thread::scope(|scope| {
+            let handles = forks.into_iter()
+                .map(|(mut fork, limits)| scope.spawn(move || {
+                    let results = fork.search(game, &limits);
+                    (results, fork)
+                }))
+                .collect::<Vec<_>>();
+
+            let results = self.search(game, &limits.split(threads, 0));
+            let forks = handles.into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>();
+
+            (results, forks)
+        });
+
+        for (fork_results, fork) in forks {
+            for (total, value) in results.iter_mut().zip(fork_results.iter()) {
+                *total += value;
+            }
+
+            self.merge(fork, game);
+        }
+
+        self.resolve_from(game, &mut KeySet::default());
+        results
+    }
+
+    fn search_tree_parallel(&mut self, game: &Game, limits: &SearchLimits) -> [usize; 4] {
+        let threads = self.threads;
+        let rollout = self.rollout.clone();
+        let seeds = (0..threads).map(|_| self.rng.next_u64()).collect::<Vec<_>>();
+
+        let nodes = self.memory.len();
+
+        // the tree is shared along with the number of simulations started so far
+        let tree = Mutex::new((self, 0));
+        let (tree, rollout) = (&tree, &*rollout);
+        let stopwatch = Stopwatch::start();
+        let stopwatch = &stopwatch;
+
+        let results = thread::scope(|scope| {
+            let handles = seeds.into_iter()
+                .map(|seed| scope.spawn(move || {
+                    let mut rng = Rng::new(seed);
+                    let mut results = [0; 4];
+                    loop {
+                        let mut game = game.to_owned();
+                        let player = game.current_player();
+
+                        let (path, known) = {
+                            let mut guard = tree.lock().unwrap();
+                            let (ref mut tree, ref mut started) = *guard;
+                            if tree.should_stop(&game, limits, *started, nodes, stopwatch) {
+                                break
+                            }
+
+                            *started += 1;
+
+                            let (path, known) = tree.descend(&mut game);
+
+                            // count the games right away so that other threads avoid
+                            // this line until its result is known
+                            for step in path.iter() {
+                                tree.memory.get_mut(&step.slot.key).unwrap().stats[step.col].1 += 1;
+                            }
+
+                            (path, known)
+                        };
+
+                        let (winner, proof, moves) = match known {
+                            Some((winner, proof)) => (winner, Some(proof), Vec::new()),
+                            None => {
+                                let moves = MCTS::rollout(rollout, &mut game, &mut rng);
+                                (game.winning_token(), None, moves)
+                            },
+                        };
+
+                        tree.lock().unwrap().0.backpropagate(path, &moves, winner, proof, true);
+
+                        let (wins, losses, ties) = outcome(player, winner);
+                        results[0] += wins;
+                        results[1] += losses;
+                        results[2] += ties;
+                        results[3] += 1;
+                    }
+
+                    results
+                }))
+                .collect::<Vec<_>>();
+
+            handles.into_iter()
+                .map(|handle| handle.join().unwrap())
+                .fold([0; 4], |mut total, results| {
+                    for (total, value) in total.iter_mut().zip(results.iter()) {
+                        *total += value;
+                    }
+
+                    total
+                })
+        });
+
         results
     }
 }
@@ -145,7 +1227,143 @@ impl MCTS {
 impl MCTS {
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
     pub fn new() -> MCTS {
-        MCTS { memory: HashMap::new() }
+        MCTS::with_seed(Rng::from_entropy().next_u64())
+    }
+
+    /// A searcher whose choices only depend on `seed`, the positions it is given and the
+    /// number of simulations it runs.
+    pub fn with_seed(seed: u64) -> MCTS {
+        MCTS {
+            memory: Memory::default(),
+            shape: None,
+            root: None,
+            check_collisions: false,
+            symmetry: true,
+            rave: None,
+            prior: None,
+            selection: Arc::new(Ucb1::default()),
+            rollout: Arc::new(UniformRandom),
+            rng: Rng::new(seed),
+
+            max_nodes: None,
+            max_bytes: None,
+
+            #[cfg(not(target_arch = "wasm32"))]
+            threads: 1,
+            #[cfg(not(target_arch = "wasm32"))]
+            parallelism: Parallelism::Root,
+        }
+    }
+
+    /// Number of positions held in memory.
+    pub fn nodes(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Limits the number of positions held in memory; `None` removes the limit.
+    pub fn set_max_nodes(&mut self, nodes: Option<usize>) {
+        self.max_nodes = nodes;
+    }
+
+    /// Limits the approximate size of the memory in bytes; `None` removes the limit.
+    pub fn set_max_bytes(&mut self, bytes: Option<usize>) {
+        self.max_bytes = bytes;
+    }
+
+    /// Number of simulations that went through `game`.
+    pub fn visits(&self, game: &Game) -> usize {
+        self.node(game).map_or(0, |node| node.stats.iter().map(|(_, games)| games).sum())
+    }
+
+    /// Makes `game` the root of the search, discarding every position that can't be
+    /// reached from it, and returns the number of simulations carried over.
+    pub fn set_root(&mut self, game: &Game) -> usize {
+        self.bind(game);
+        self.prune_to(game);
+        self.root = Some(game.clone());
+
+        self.visits(game)
+    }
+
+    /// Runs up to `iterations` simulations from `game` on the calling thread and returns
+    /// how many were run, which is `0` once its outcome is proven.
+    ///
+    /// Calling this repeatedly amounts to an incremental search that can be interrupted
+    /// between slices.
+    pub fn think_slice(&mut self, game: &Game, iterations: usize) -> usize {
+        self.follow(game);
+        if self.node(game).is_some_and(|node| node.proof.is_some()) {
+            return 0
+        }
+
+        self.search(game, &SearchLimits::new().iterations(iterations))[3]
+    }
+
+    /// Evicts every position that can't be reached from `game` and returns how many were
+    /// evicted.
+    ///
+    /// `think` does this on its own when a memory limit is set and the root moves, so
+    /// that the freed room goes to the positions still ahead.
+    pub fn prune_to(&mut self, game: &Game) -> usize {
+        let reachable = self.reachable(game);
+        let before = self.memory.len();
+        self.memory.retain(|key, _| reachable.contains(key));
+
+        before - self.memory.len()
+    }
+
+    /// Enables storing the full state alongside each key so that colliding positions
+    /// are detected and replaced instead of sharing statistics.
+    pub fn set_collision_check(&mut self, enabled: bool) {
+        self.check_collisions = enabled;
+    }
+
+    /// Shares what is learnt about a position with its mirror image in games where they
+    /// are worth the same, which is the default.
+    pub fn set_symmetry(&mut self, enabled: bool) {
+        self.symmetry = enabled;
+    }
+
+    /// Enables RAVE with the default schedule, along with the lower UCB1 exploration it
+    /// works best with.
+    pub fn use_rave(&mut self) {
+        self.set_rave(Some(BetaSchedule::default()));
+        self.set_ucb1(RAVE_EXPLORATION);
+    }
+
+    /// Uses `hbot`'s column scores as priors with the given weight; see `set_prior`.
+    pub fn use_column_score_prior(&mut self, weight: f64) {
+        self.set_prior(ColumnScore, weight);
+    }
+
+    /// Stops seeding new positions with priors.
+    pub fn clear_prior(&mut self) {
+        self.prior = None;
+    }
+
+    /// Selects moves with UCB1 using the given exploration constant.
+    pub fn set_ucb1(&mut self, exploration: f64) {
+        self.selection = Arc::new(Ucb1::new(exploration));
+    }
+
+    /// Selects moves randomly in proportion to their win rate.
+    pub fn set_weighted_random(&mut self) {
+        self.selection = Arc::new(WeightedRandom);
+    }
+
+    /// Plays out games with uniformly random moves, which is the default.
+    pub fn set_uniform_rollout(&mut self) {
+        self.rollout = Arc::new(UniformRandom);
+    }
+
+    /// Plays out games taking wins and blocking losses that are one move away.
+    pub fn set_tactical_rollout(&mut self) {
+        self.rollout = Arc::new(Tactical);
+    }
+
+    /// Plays out games like `hbot`, with a random move with probability `epsilon`.
+    pub fn set_heuristic_rollout(&mut self, epsilon: f64) {
+        self.rollout = Arc::new(Heuristic::new(epsilon));
     }
 }
 
@@ -162,28 +1380,38 @@ impl MCTS {
         ].into_boxed_slice()
     }
 
+    /// Searches from `game` and returns a `SearchReport` as a plain object; see
+    /// `SearchReport::to_json`.
     #[wasm_bindgen(js_name = "think")]
-    pub fn think(&mut self, game: &Game, duration: usize) -> Box<[JsValue]> {
-        let start = now();
-        let mut results = [0 as usize; 4];
-        while now() - start < duration {
-            let (wins, losses, ties) = self.simulate(game.to_owned());
-            results[0] += wins;
-            results[1] += losses;
-            results[2] += ties;
-            results[3] += 1;
+    pub fn think(&mut self, game: &Game, limits: &SearchLimits) -> JsValue {
+        let stopwatch = Stopwatch::start();
+        self.follow(game);
+        let results = self.search(game, limits);
+
+        parse_json(&self.report(game, results, stopwatch.elapsed()).to_json())
+    }
+
+    /// Reports on `game` without searching.
+    #[wasm_bindgen(js_name = "report")]
+    pub fn report_wasm(&self, game: &Game) -> JsValue {
+        parse_json(&self.report(game, [0; 4], Duration::from_secs(0)).to_json())
+    }
+
+    #[wasm_bindgen(js_name = "advance")]
+    pub fn advance_wasm(&mut self, col: usize) -> Result<usize, JsValue> {
+        match self.advance(col) {
+            Err(err) => Err(format!("{}", err).into()),
+            Ok(visits) => Ok(visits),
         }
+    }
 
-        vec![
-            JsValue::from(results[0] as u32),
-            JsValue::from(results[1] as u32),
-            JsValue::from(results[2] as u32),
-            JsValue::from(results[3] as u32),
-        ].into_boxed_slice()
+    #[wasm_bindgen(js_name = "best_move")]
+    pub fn best_move_wasm(&self, game: &Game) -> Option<usize> {
+        self.best_move(game)
     }
 
     #[wasm_bindgen(js_name = "move_weights")]
-    pub fn move_weights_wasm(&self, state: State, moves: Box<[JsValue]>) -> Result<Box<[JsValue]>, JsValue> {
+    pub fn move_weights_wasm(&self, game: &Game, moves: Box<[JsValue]>) -> Result<Box<[JsValue]>, JsValue> {
         let mut resolved_moves = Vec::<usize>::new();
         for value in moves.iter() {
             if let Some(num) = value.as_f64() {
@@ -193,9 +1421,375 @@ impl MCTS {
             }
         }
 
-        Ok(self.move_weights(&state, &resolved_moves).into_iter()
+        Ok(self.move_weights(game, &resolved_moves).into_iter()
             .map(|col| JsValue::from(col))
             .collect::<Vec<_>>()
             .into_boxed_slice())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_mcts_finds_immediate_win() {
+        let mut game = Game::new();
+        for col in [0, 0, 1, 1, 2, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        let mut mcts = MCTS::with_seed(13);
+        for _ in 0..2000 {
+            mcts.simulate(game.clone());
+        }
+
+        assert_eq!(mcts.best_move(&game), Some(3));
+    }
+
+    #[test]
+    fn test_mcts_proves_wins_and_losses() {
+        let mut game = Game::new();
+        for col in [0, 0, 1, 1, 2, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        let mut mcts = MCTS::with_seed(3);
+        mcts.think(&game, SearchLimits::new().iterations(100));
+        assert_eq!(mcts.proof(&game, 3), Some(Proof::Win));
+        assert_eq!(mcts.best_move(&game), Some(3));
+
+        // the opponent has to block, every other move loses right away
+        let mut game = Game::new();
+        for col in [0, 0, 1, 1, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        let mut mcts = MCTS::with_seed(3);
+        mcts.think(&game, SearchLimits::new().iterations(2000));
+        for col in [0, 1, 2, 4, 5, 6].iter() {
+            assert_eq!(mcts.proof(&game, *col), Some(Proof::Loss));
+        }
+        assert_eq!(mcts.best_move(&game), Some(3));
+    }
+
+    #[test]
+    fn test_mcts_seeded_is_deterministic() {
+        let game = Game::new();
+        let moves = game.valid_moves();
+
+        let weights = (0..2).map(|_| {
+            let mut mcts = MCTS::with_seed(7);
+            for _ in 0..1000 {
+                mcts.simulate(game.clone());
+            }
+
+            mcts.move_weights(&game, &moves)
+        }).collect::<Vec<_>>();
+
+        assert_eq!(weights[0], weights[1]);
+    }
+
+    #[test]
+    fn test_mcts_think_limits() {
+        let mut game = Game::new();
+        for col in [0, 0, 1, 1, 2, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        let mut mcts = MCTS::with_seed(1);
+        assert_eq!(mcts.think(&game, SearchLimits::new().iterations(500)).iterations, 500);
+        assert!(mcts.think(&game, Duration::from_millis(50)).iterations > 0);
+
+        let mut mcts = MCTS::with_seed(1);
+        mcts.think(&Game::new(), SearchLimits::new().nodes(300));
+        assert_eq!(mcts.nodes(), 300);
+
+        let mut mcts = MCTS::with_seed(1);
+        let report = mcts.think(&game, SearchLimits::new().iterations(100_000).stop_when_decided());
+        assert!(report.iterations < 100_000);
+    }
+
+    #[test]
+    fn test_mcts_node_limit_on_reused_tree() {
+        let game = Game::new();
+
+        let mut mcts = MCTS::with_seed(2);
+        mcts.think(&game, SearchLimits::new().iterations(2000));
+        let before = mcts.nodes();
+
+        // only the positions added by this search count against its budget
+        let report = mcts.think(&game, SearchLimits::new().nodes(500));
+        assert!(report.iterations > 0);
+        assert_eq!(mcts.nodes(), before + 500);
+    }
+
+    #[test]
+    fn test_mcts_memory_limit() {
+        let mut game = Game::new();
+
+        let mut mcts = MCTS::with_seed(14);
+        mcts.set_max_nodes(Some(100));
+        for _ in 0..1000 {
+            mcts.simulate(game.clone());
+        }
+
+        assert_eq!(mcts.nodes(), 100);
+
+        game.drop(3).unwrap();
+        game.drop(3).unwrap();
+        assert!(mcts.prune_to(&game) > 0);
+        assert!(mcts.memory.values().all(|node| node.ply >= 2));
+    }
+
+    #[test]
+    fn test_mcts_limited_memory_follows_the_game() {
+        let mut game = Game::new();
+
+        let mut mcts = MCTS::with_seed(3);
+        mcts.set_max_nodes(Some(200));
+        mcts.think(&game, SearchLimits::new().iterations(2000));
+        assert_eq!(mcts.nodes(), 200);
+
+        // positions off the line played are dropped, even ones further into the game
+        for col in [3, 2, 4].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        mcts.think(&game, SearchLimits::new().iterations(1));
+        assert!(mcts.nodes() < 200);
+        assert_eq!(mcts.reachable(&game).len(), mcts.nodes());
+    }
+
+    #[test]
+    fn test_mcts_advance() {
+        let game = Game::new();
+
+        let mut mcts = MCTS::with_seed(5);
+        mcts.think(&game, SearchLimits::new().iterations(2000));
+        assert!(mcts.advance(7).is_err());
+
+        let before = mcts.nodes();
+        let col = mcts.best_move(&game).unwrap();
+        let carried = mcts.advance(col).unwrap();
+
+        let mut next = game.clone();
+        next.drop(col).unwrap();
+        assert_eq!(carried, mcts.visits(&next));
+        assert!(carried > 0);
+        assert!(mcts.nodes() < before);
+        assert_eq!(mcts.root().unwrap().key(), next.key());
+    }
+
+    #[test]
+    fn test_mcts_ponder() {
+        let mut game = Game::new();
+
+        let mut mcts = MCTS::with_seed(9);
+        let (col, simulations) = mcts.ponder(&game.clone(), || {
+            thread::sleep(Duration::from_millis(50));
+            3
+        });
+
+        assert!(simulations > 0);
+        assert!(mcts.visits(&game) > 0);
+
+        game.drop(col).unwrap();
+        assert!(mcts.advance(col).unwrap() > 0);
+    }
+
+    #[test]
+    fn test_mcts_think_slice_stops_when_proven() {
+        let mut game = Game::new();
+        for col in [0, 0, 1, 1, 2, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        let mut mcts = MCTS::with_seed(3);
+        while mcts.think_slice(&game, 64) > 0 {}
+        assert_eq!(mcts.proof(&game, 3), Some(Proof::Win));
+    }
+
+    #[test]
+    fn test_mcts_symmetry() {
+        let mut left = Game::new();
+        left.drop(1).unwrap();
+        let mut right = Game::new();
+        right.drop(5).unwrap();
+
+        let mut mcts = MCTS::with_seed(11);
+        mcts.think(&left, SearchLimits::new().iterations(2000));
+        assert_eq!(mcts.visits(&right), mcts.visits(&left));
+
+        let mirrored = right.valid_moves().iter().rev().cloned().collect::<Vec<_>>();
+        assert_eq!(mcts.move_weights(&right, &mirrored), mcts.move_weights(&left, &left.valid_moves()));
+        assert_eq!(mcts.best_move(&right).map(|col| 6 - col), mcts.best_move(&left));
+
+        let mut mcts = MCTS::with_seed(11);
+        mcts.set_symmetry(false);
+        mcts.think(&left, SearchLimits::new().iterations(2000));
+        assert_eq!(mcts.visits(&right), 0);
+    }
+
+    #[test]
+    fn test_mcts_rave() {
+        let mut game = Game::new();
+        for col in [0, 0, 1, 1, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        let mut mcts = MCTS::with_seed(4);
+        mcts.use_rave();
+        mcts.think(&game, SearchLimits::new().iterations(1000));
+        assert_eq!(mcts.best_move(&game), Some(3));
+
+        let node = mcts.node(&game).unwrap();
+        let slot = mcts.slot(&game);
+        assert!(node.amaf[slot.col(3)].1 > node.stats[slot.col(3)].1);
+    }
+
+    #[test]
+    fn test_mcts_prior() {
+        let mut game = Game::new();
+        for col in [3, 3, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        let mut mcts = MCTS::with_seed(6);
+        mcts.use_column_score_prior(3.0);
+        mcts.think(&game, SearchLimits::new().iterations(2));
+
+        // the root is expanded by the first simulation and the best prior is tried next
+        let priors = ColumnScore.evaluate(&game);
+        let node = mcts.node(&game).unwrap();
+        let slot = mcts.slot(&game);
+        assert_eq!((0..7).map(|col| node.priors[slot.col(col)]).collect::<Vec<_>>(), priors);
+
+        let best = priors.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let tried = (0..7).find(|col| node.stats[slot.col(*col)].1 > 0).unwrap();
+        assert_eq!(priors[tried], best);
+    }
+
+    #[test]
+    fn test_mcts_report() {
+        let mut game = Game::new();
+        for col in [0, 0, 1, 1, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        let mut mcts = MCTS::with_seed(8);
+        let report = mcts.think(&game, SearchLimits::new().iterations(2000));
+        assert_eq!(report.moves.iter().map(|report| report.col).collect::<Vec<_>>(), game.valid_moves());
+        assert_eq!(report.moves.iter().map(|report| report.visits).sum::<usize>() + 1, report.iterations);
+        assert_eq!(report.nodes, mcts.nodes());
+
+        // only blocking doesn't lose right away
+        assert_eq!(report.best_move, Some(3));
+        assert_eq!(report.principal_variation[0], 3);
+        for report in report.moves.iter() {
+            assert!(report.interval.0 <= report.value && report.value <= report.interval.1);
+            if report.col != 3 {
+                assert_eq!(report.proof, Some(Proof::Loss));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mcts_tree() {
+        let game = Game::new();
+
+        let mut mcts = MCTS::with_seed(12);
+        mcts.think(&game, SearchLimits::new().iterations(3000));
+
+        let tree = mcts.tree(&game, 2, 50);
+        assert_eq!(tree.key, game.key());
+        assert_eq!(tree.visits, mcts.visits(&game));
+        assert!(!tree.edges.is_empty());
+        for edge in tree.edges.iter() {
+            assert!(edge.report.visits >= 50);
+            for edge in edge.child.edges.iter() {
+                assert!(edge.report.visits >= 50);
+                assert!(edge.child.edges.is_empty());
+            }
+        }
+
+        assert!(mcts.tree(&game, 0, 0).edges.is_empty());
+    }
+
+    #[test]
+    fn test_mcts_write_read() {
+        let game = Game::new();
+
+        let mut mcts = MCTS::with_seed(15);
+        mcts.set_collision_check(true);
+        for _ in 0..500 {
+            mcts.simulate(game.clone());
+        }
+
+        let mut bytes = Vec::new();
+        mcts.write(&mut bytes).unwrap();
+
+        let mut loaded = MCTS::with_seed(16);
+        let mut other = game.clone();
+        other.drop(0).unwrap();
+        loaded.think(&other, SearchLimits::new().iterations(50));
+        assert_eq!(loaded.read(&mut bytes.as_slice(), &game).unwrap(), mcts.nodes());
+        assert!(loaded.root().is_none());
+        assert!(loaded.advance(3).is_err());
+
+        let moves = game.valid_moves();
+        assert_eq!(loaded.move_weights(&game, &moves), mcts.move_weights(&game, &moves));
+
+        assert!(loaded.read(&mut bytes.as_slice(), &Game::custom(8, 6, 4)).is_err());
+        assert!(loaded.read(&mut &b"C4MX"[..], &game).is_err());
+    }
+
+    #[test]
+    fn test_mcts_parallel_think() {
+        let mut game = Game::new();
+        for col in [0, 0, 1, 1, 2, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        for parallelism in [Parallelism::Root, Parallelism::Tree].iter() {
+            let mut mcts = MCTS::with_seed(17);
+            mcts.set_threads(4, *parallelism);
+
+            let report = mcts.think(&game, SearchLimits::new().iterations(4000));
+            assert_eq!(report.iterations, 4000);
+            assert_eq!(report.wins + report.losses + report.draws, report.iterations);
+
+            assert_eq!(mcts.best_move(&game), Some(3));
+        }
+    }
+
+    #[test]
+    fn test_mcts_continues_after_root_parallel_proofs() {
+        let mut rng = Rng::new(7);
+        for seed in 0..150 {
+            let mut game = Game::custom(5, 4, 3);
+            for _ in 0..rng.below(6) {
+                let moves = game.valid_moves();
+                game.drop(moves[rng.below(moves.len())]).unwrap();
+                if game.over() {
+                    break
+                }
+            }
+
+            if game.over() {
+                continue
+            }
+
+            // merged proofs must leave no position with only lost moves to explore
+            let mut mcts = MCTS::with_seed(seed);
+            mcts.set_threads(8, Parallelism::Root);
+            for _ in 0..5 {
+                mcts.think(&game, SearchLimits::new().iterations(80));
+            }
+
+            mcts.set_threads(1, Parallelism::Root);
+            mcts.think(&game, SearchLimits::new().iterations(200));
+        }
+    }
+}
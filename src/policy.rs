@@ -0,0 +1,276 @@
+use common::Rng;
+use game::Game;
+use hbot;
+
+/// Chooses which move to explore from a node of the search tree.
+pub trait SelectionPolicy: Send + Sync {
+    /// Returns an index into `moves`, given the `(score, games)` record of every column
+    /// of the node.
+    fn select(&self, stats: &[(f64, usize)], moves: &[usize], rng: &mut Rng) -> usize;
+}
+
+/// Estimates how promising the moves of a position are before any simulation.
+pub trait Evaluator: Send + Sync {
+    /// Returns a prior in `[0, 1]` for every column of `game`, full or not.
+    fn evaluate(&self, game: &Game) -> Vec<f64>;
+}
+
+/// Chooses moves when playing a game out past the leaves of the search tree.
+pub trait RolloutPolicy: Send + Sync {
+    fn play(&self, game: &Game, rng: &mut Rng) -> usize;
+}
+
+pub fn winrate((score, games): (f64, usize)) -> f64 {
+    if games == 0 {
+        0.5
+    } else {
+        score / games as f64
+    }
+}
+
+pub fn random_weighted(weights: Vec<f64>, rng: &mut Rng) -> usize {
+    let n = weights.len();
+    if n == 0 {
+        panic!("no values")
+    }
+
+    let sum = weights.iter().fold(0.0, |sum, w| sum + w);
+    if sum == 0.0 {
+        return rng.below(n)
+    }
+
+    let value = rng.next_f64();
+    let mut progress = 0.0;
+    for (i, weight) in weights.iter().enumerate() {
+        let normalized = weight / sum;
+        if value < progress + normalized {
+            return i
+        }
+
+        progress += normalized;
+    }
+
+    unreachable!()
+}
+
+/// Upper Confidence Bound applied to trees.
+///
+/// Unvisited moves are always tried first, in random order.
+pub struct Ucb1 {
+    pub exploration: f64,
+}
+
+impl Ucb1 {
+    pub fn new(exploration: f64) -> Ucb1 {
+        Ucb1 { exploration }
+    }
+}
+
+impl Default for Ucb1 {
+    fn default() -> Ucb1 {
+        Ucb1::new(2f64.sqrt())
+    }
+}
+
+impl SelectionPolicy for Ucb1 {
+    fn select(&self, stats: &[(f64, usize)], moves: &[usize], rng: &mut Rng) -> usize {
+        let unvisited = moves.iter()
+            .enumerate()
+            .filter(|(_, col)| stats[**col].1 == 0)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        if !unvisited.is_empty() {
+            return unvisited[rng.below(unvisited.len())]
+        }
+
+        let log_visits = (moves.iter().map(|col| stats[*col].1).sum::<usize>() as f64).ln();
+
+        let mut best = (0, f64::NEG_INFINITY);
+        for (i, col) in moves.iter().enumerate() {
+            let (score, games) = stats[*col];
+            let value = score / games as f64 + self.exploration * (log_visits / games as f64).sqrt();
+            if value > best.1 {
+                best = (i, value);
+            }
+        }
+
+        best.0
+    }
+}
+
+/// How much weight all-moves-as-first statistics get against the regular ones, as a
+/// function of the number of games `n` of a move and its AMAF games `ñ`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BetaSchedule {
+    /// `β = sqrt(k / (3n + k))`, which gives both statistics equal weight after `k`
+    /// games.
+    Equivalence(f64),
+    /// `β = ñ / (n + ñ + 4b²nñ)`, which minimises the mean squared error of the blend
+    /// given the bias `b` of the AMAF statistics.
+    MinimumError(f64),
+}
+
+impl BetaSchedule {
+    pub fn beta(&self, games: usize, amaf_games: usize) -> f64 {
+        let (n, amaf_n) = (games as f64, amaf_games as f64);
+        match *self {
+            BetaSchedule::Equivalence(k) => (k / (3.0 * n + k)).sqrt(),
+            BetaSchedule::MinimumError(bias) => amaf_n / (n + amaf_n + 4.0 * bias * bias * n * amaf_n),
+        }
+    }
+}
+
+impl Default for BetaSchedule {
+    fn default() -> BetaSchedule {
+        BetaSchedule::Equivalence(300.0)
+    }
+}
+
+/// Blends the `(score, games)` records of moves with their all-moves-as-first records,
+/// keeping the number of games so that the exploration terms of selection policies are
+/// unaffected.
+pub fn blend(stats: &[(f64, usize)], amaf: &[(f64, usize)], schedule: BetaSchedule) -> Vec<(f64, usize)> {
+    stats.iter()
+        .zip(amaf.iter())
+        .map(|(&(score, games), &(amaf_score, amaf_games))| {
+            if games == 0 || amaf_games == 0 {
+                return (score, games)
+            }
+
+            let beta = schedule.beta(games, amaf_games);
+            let value = (1.0 - beta) * score / games as f64 + beta * amaf_score / amaf_games as f64;
+            (value * games as f64, games)
+        })
+        .collect()
+}
+
+/// Adds a progressive bias of `weight * prior / (games + 1)` to the win rate of every
+/// move that has been played, so that priors matter less as moves get visited.
+pub fn bias(stats: &[(f64, usize)], priors: &[f64], weight: f64) -> Vec<(f64, usize)> {
+    stats.iter()
+        .zip(priors.iter())
+        .map(|(&(score, games), prior)| {
+            let games_f = games as f64;
+            (score + weight * prior * games_f / (games_f + 1.0), games)
+        })
+        .collect()
+}
+
+/// Samples moves in proportion to their win rate.
+pub struct WeightedRandom;
+
+impl SelectionPolicy for WeightedRandom {
+    fn select(&self, stats: &[(f64, usize)], moves: &[usize], rng: &mut Rng) -> usize {
+        random_weighted(moves.iter().map(|col| winrate(stats[*col])).collect(), rng)
+    }
+}
+
+/// Plays any valid move with equal probability.
+pub struct UniformRandom;
+
+impl RolloutPolicy for UniformRandom {
+    fn play(&self, game: &Game, rng: &mut Rng) -> usize {
+        let moves = game.valid_moves();
+        moves[rng.below(moves.len())]
+    }
+}
+
+/// Takes a winning move if there is one, otherwise blocks the opponent's, otherwise
+/// plays randomly.
+pub struct Tactical;
+
+impl RolloutPolicy for Tactical {
+    fn play(&self, game: &Game, rng: &mut Rng) -> usize {
+        let moves = game.valid_moves();
+        let player = game.current_player();
+        for token in [player, player.other()].iter() {
+            if let Some(col) = moves.iter().find(|col| game.winning_move(**col, *token)) {
+                return *col
+            }
+        }
+
+        moves[rng.below(moves.len())]
+    }
+}
+
+/// Plays like `hbot`, except for a random move with probability `epsilon`.
+pub struct Heuristic {
+    pub epsilon: f64,
+}
+
+impl Heuristic {
+    pub fn new(epsilon: f64) -> Heuristic {
+        Heuristic { epsilon }
+    }
+}
+
+impl Default for Heuristic {
+    fn default() -> Heuristic {
+        Heuristic::new(0.1)
+    }
+}
+
+impl RolloutPolicy for Heuristic {
+    fn play(&self, game: &Game, rng: &mut Rng) -> usize {
+        if rng.next_f64() < self.epsilon {
+            UniformRandom.play(game, rng)
+        } else {
+            hbot::next_move(game, rng)
+        }
+    }
+}
+
+/// `hbot`'s score of each column for the player to move, relative to the win length.
+pub struct ColumnScore;
+
+impl Evaluator for ColumnScore {
+    fn evaluate(&self, game: &Game) -> Vec<f64> {
+        (0..game.cols())
+            .map(|col| hbot::calculate_column_score(game, game.current_player(), col) as f64 / game.win_len() as f64)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tactical_wins_then_blocks() {
+        let mut rng = Rng::new(1);
+
+        // player 1 can win in column 3 and player 2 in column 4
+        let mut game = Game::new();
+        for col in [0, 4, 1, 4, 2, 4].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        for _ in 0..20 {
+            assert_eq!(Tactical.play(&game, &mut rng), 3);
+        }
+
+        // player 1 can only block
+        let mut game = Game::new();
+        for col in [0, 4, 0, 4, 1, 4].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        for _ in 0..20 {
+            assert_eq!(Tactical.play(&game, &mut rng), 4);
+        }
+    }
+
+    #[test]
+    fn test_heuristic_plays_valid_moves() {
+        let mut rng = Rng::new(2);
+        for epsilon in [0.0, 0.5, 1.0].iter() {
+            let policy = Heuristic::new(*epsilon);
+            let mut game = Game::custom(5, 4, 3);
+            while !game.over() {
+                let col = policy.play(&game, &mut rng);
+                game.drop(col).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use board::Token;
+use game::{Game, ParseError};
+
+#[derive(Debug)]
+pub struct InvalidRecordError(pub usize);
+
+impl fmt::Display for InvalidRecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid game record at line {}", self.0)
+    }
+}
+
+impl error::Error for InvalidRecordError {}
+
+#[derive(Debug)]
+pub struct MissingHeaderError(pub &'static str);
+
+impl fmt::Display for MissingHeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "game record has no {} header", self.0)
+    }
+}
+
+impl error::Error for MissingHeaderError {}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameResult {
+    Win(Token),
+    Draw,
+    Unfinished,
+}
+
+impl GameResult {
+    pub fn of(game: &Game) -> GameResult {
+        match game.winning_token() {
+            Some(token) => GameResult::Win(token),
+            None if game.over() => GameResult::Draw,
+            None => GameResult::Unfinished,
+        }
+    }
+}
+
+/// Written as in chess notation: `1-0`, `0-1`, `1/2-1/2` or `*`.
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameResult::Win(Token::Player1) => write!(f, "1-0"),
+            GameResult::Win(Token::Player2) => write!(f, "0-1"),
+            GameResult::Draw => write!(f, "1/2-1/2"),
+            GameResult::Unfinished => write!(f, "*"),
+        }
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<GameResult, ()> {
+        match s {
+            "1-0" => Ok(GameResult::Win(Token::Player1)),
+            "0-1" => Ok(GameResult::Win(Token::Player2)),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*" => Ok(GameResult::Unfinished),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Move {
+    pub col: usize,
+
+    /// How good the player making the move thought it was, from 0 to 1.
+    pub value: Option<f64>,
+    pub comment: Option<String>,
+}
+
+/// A game along with who played it and how it ended.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Record {
+    pub players: [String; 2],
+
+    /// Settings the bots played with, in free form.
+    pub settings: Option<String>,
+
+    pub cols: usize,
+    pub rows: usize,
+    pub win_len: usize,
+
+    /// Day the game was played on, as `YYYY-MM-DD`; see `date`.
+    pub date: Option<String>,
+    pub result: GameResult,
+    pub moves: Vec<Move>,
+}
+
+impl Record {
+    /// A record of the moves played in `game` so far, without values or comments.
+    pub fn new(game: &Game, players: [String; 2]) -> Record {
+        Record {
+            players,
+            settings: None,
+            cols: game.cols(),
+            rows: game.rows(),
+            win_len: game.win_len(),
+            date: None,
+            result: GameResult::of(game),
+            moves: game.history().iter()
+                .map(|col| Move { col: *col, value: None, comment: None })
+                .collect(),
+        }
+    }
+
+    /// Plays the recorded moves on an empty board.
+    pub fn game(&self) -> Result<Game, Box<dyn error::Error>> {
+        let mut game = Game::try_custom(self.cols, self.rows, self.win_len)?;
+        for (i, m) in self.moves.iter().enumerate() {
+            if game.drop(m.col).is_err() {
+                return Err(Box::new(ParseError::IllegalMove(i)))
+            }
+        }
+
+        Ok(game)
+    }
+
+    /// Writes the record as text, with headers followed by a blank line and a move per
+    /// line:
+    ///
+    /// ```text
+    /// Player1: human
+    /// Player2: mcts
+    /// Settings: think 1s, rave
+    /// Size: 7x6
+    /// WinLength: 4
+    /// Date: 2020-05-17
+    /// Result: 0-1
+    ///
+    /// 4
+    /// 4 0.541
+    /// 3 ; blocks the diagonal
+    /// ```
+    ///
+    /// Moves are columns numbered from 1, optionally followed by their value and a
+    /// comment after `;`. Only `Size` and `WinLength` are required.
+    pub fn write<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "Player1: {}", self.players[0])?;
+        writeln!(w, "Player2: {}", self.players[1])?;
+        if let Some(ref settings) = self.settings {
+            writeln!(w, "Settings: {}", settings)?;
+        }
+
+        writeln!(w, "Size: {}x{}", self.cols, self.rows)?;
+        writeln!(w, "WinLength: {}", self.win_len)?;
+        if let Some(ref date) = self.date {
+            writeln!(w, "Date: {}", date)?;
+        }
+
+        writeln!(w, "Result: {}", self.result)?;
+        writeln!(w)?;
+
+        for m in self.moves.iter() {
+            let mut line = (m.col + 1).to_string();
+            if let Some(value) = m.value {
+                line.push_str(&format!(" {:.3}", value));
+            }
+
+            if let Some(ref comment) = m.comment {
+                line.push_str(" ; ");
+                line.push_str(&comment.replace('\n', " "));
+            }
+
+            writeln!(w, "{}", line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a record written by `write`, ignoring unknown headers.
+    pub fn read<R: io::BufRead>(r: &mut R) -> Result<Record, Box<dyn error::Error>> {
+        let mut record = Record {
+            players: [String::from("?"), String::from("?")],
+            settings: None,
+            cols: 0,
+            rows: 0,
+            win_len: 0,
+            date: None,
+            result: GameResult::Unfinished,
+            moves: Vec::new(),
+        };
+
+        let mut lines = r.lines().enumerate();
+
+        // headers, up to the first blank line
+        let (mut size, mut win_len) = (None, None);
+        for (i, line) in &mut lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                break
+            }
+
+            let invalid = || Box::new(InvalidRecordError(i + 1));
+            let (name, value) = match line.find(':') {
+                None => return Err(invalid()),
+                Some(at) => (line[..at].trim(), line[at + 1..].trim().to_string()),
+            };
+
+            match name {
+                "Player1" => record.players[0] = value,
+                "Player2" => record.players[1] = value,
+                "Settings" => record.settings = Some(value),
+                "Size" => {
+                    let mut dimensions = value.split('x').map(usize::from_str);
+                    size = match (dimensions.next(), dimensions.next(), dimensions.next()) {
+                        (Some(Ok(cols)), Some(Ok(rows)), None) => Some((cols, rows)),
+                        _ => return Err(invalid()),
+                    };
+                },
+                "WinLength" => win_len = Some(usize::from_str(&value).map_err(|_| invalid())?),
+                "Date" => record.date = Some(value),
+                "Result" => record.result = value.parse().map_err(|_| invalid())?,
+                _ => (),
+            }
+        }
+
+        let (cols, rows) = size.ok_or(MissingHeaderError("Size"))?;
+        record.cols = cols;
+        record.rows = rows;
+        record.win_len = win_len.ok_or(MissingHeaderError("WinLength"))?;
+
+        for (i, line) in lines {
+            let line = line?;
+            let (text, comment) = match line.find(';') {
+                None => (&line[..], None),
+                Some(at) => (&line[..at], Some(line[at + 1..].trim().to_string())),
+            };
+
+            let mut fields = text.split_whitespace();
+            let col = match fields.next() {
+                // blank lines between moves
+                None if comment.is_none() => continue,
+                None => return Err(Box::new(InvalidRecordError(i + 1))),
+                Some(col) => match usize::from_str(col) {
+                    Ok(col) if col >= 1 => col - 1,
+                    _ => return Err(Box::new(InvalidRecordError(i + 1))),
+                },
+            };
+
+            let value = match fields.next() {
+                None => None,
+                Some(value) => Some(f64::from_str(value).map_err(|_| InvalidRecordError(i + 1))?),
+            };
+
+            if fields.next().is_some() {
+                return Err(Box::new(InvalidRecordError(i + 1)))
+            }
+
+            record.moves.push(Move { col, value, comment });
+        }
+
+        Ok(record)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = io::BufWriter::new(File::create(path)?);
+        self.write(&mut file)?;
+        file.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Record, Box<dyn error::Error>> {
+        Record::read(&mut io::BufReader::new(File::open(path)?))
+    }
+}
+
+/// The UTC day of `time` as `YYYY-MM-DD`.
+pub fn date(time: SystemTime) -> String {
+    let days = time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() / 86400);
+
+    // days since 0000-03-01, in 400-year eras of 146097 days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+
+    // months starting from March
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let (year, month) = if month < 10 {
+        (era * 400 + year_of_era, month + 3)
+    } else {
+        (era * 400 + year_of_era + 1, month - 9)
+    };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_record_round_trip() {
+        let game = Game::from_moves("4455667").unwrap();
+        let mut record = Record::new(&game, [String::from("human"), String::from("mcts")]);
+        record.settings = Some(String::from("think 1s, rave"));
+        record.date = Some(String::from("2020-05-17"));
+        record.moves[1].value = Some(0.5);
+        record.moves[6].comment = Some(String::from("wins\nthe game"));
+        assert_eq!(record.result, GameResult::Win(Token::Player1));
+
+        let mut text = Vec::new();
+        record.write(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("Size: 7x6\nWinLength: 4\nDate: 2020-05-17\nResult: 1-0\n\n4\n4 0.500\n"));
+        assert!(text.ends_with("7 ; wins the game\n"));
+
+        let read = Record::read(&mut text.as_bytes()).unwrap();
+        record.moves[6].comment = Some(String::from("wins the game"));
+        assert_eq!(read, record);
+        assert_eq!(read.game().unwrap().key(), game.key());
+    }
+
+    #[test]
+    fn test_record_errors() {
+        let read = |text: &str| Record::read(&mut text.as_bytes()).map_err(|err| err.to_string());
+
+        assert_eq!(read("Size: 7x6\n\n4\n").unwrap_err(), "game record has no WinLength header");
+        assert_eq!(read("Size: 7\nWinLength: 4\n").unwrap_err(), "invalid game record at line 1");
+        assert_eq!(read("Size: 7x6\nWinLength: 4\n\n4\nx\n").unwrap_err(), "invalid game record at line 5");
+        assert_eq!(read("Size: 7x6\nWinLength: 4\n\n4 0.5 ; fine\n4 high\n").unwrap_err(), "invalid game record at line 5");
+
+        let record = Record::read(&mut "Size: 3x2\nWinLength: 2\n\n1\n1\n1\n".as_bytes()).unwrap();
+        assert!(record.game().is_err());
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(date(UNIX_EPOCH), "1970-01-01");
+        assert_eq!(date(UNIX_EPOCH + Duration::from_secs(1_000_000_000)), "2001-09-09");
+        assert_eq!(date(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000-02-29");
+    }
+}
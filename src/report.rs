@@ -0,0 +1,197 @@
+use std::fmt;
+use std::time::Duration;
+
+use mcts::Proof;
+
+// z-score of a 95% confidence level
+const Z: f64 = 1.96;
+
+/// Wilson score interval, at a 95% confidence level, of the mean of `games` results
+/// adding up to `score`.
+pub fn wilson_interval(score: f64, games: usize) -> (f64, f64) {
+    if games == 0 {
+        return (0.0, 1.0)
+    }
+
+    let n = games as f64;
+    let p = score / n;
+    let z2 = Z * Z;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let margin = Z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+
+    ((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+pub fn proof_name(proof: Option<Proof>) -> Option<&'static str> {
+    match proof {
+        Some(Proof::Win) => Some("win"),
+        Some(Proof::Loss) => Some("loss"),
+        Some(Proof::Draw) => Some("draw"),
+        None => None,
+    }
+}
+
+/// What the search found out about one legal move.
+#[derive(Clone, Debug)]
+pub struct MoveReport {
+    pub col: usize,
+    pub visits: usize,
+
+    /// Mean result of the simulations through the move for the player making it, with
+    /// draws worth half a win.
+    pub value: f64,
+    /// Confidence interval of `value`; see `wilson_interval`.
+    pub interval: (f64, f64),
+    pub proof: Option<Proof>,
+}
+
+impl MoveReport {
+    pub fn new(col: usize, (score, games): (f64, usize), proof: Option<Proof>) -> MoveReport {
+        MoveReport {
+            col,
+            visits: games,
+            value: if games == 0 { 0.5 } else { score / games as f64 },
+            interval: wilson_interval(score, games),
+            proof,
+        }
+    }
+
+    /// The report as a JSON object with the same field names, where the interval is a
+    /// pair and unknown proofs are `null`.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"col":{},"visits":{},"value":{},"interval":[{},{}],"proof":{}}}"#,
+            self.col,
+            self.visits,
+            self.value,
+            self.interval.0,
+            self.interval.1,
+            json_proof(self.proof),
+        )
+    }
+}
+
+pub fn json_proof(proof: Option<Proof>) -> String {
+    proof_name(proof).map_or("null".to_string(), |name| format!(r#""{}""#, name))
+}
+
+/// Outcome of a search from one position.
+#[derive(Clone, Debug)]
+pub struct SearchReport {
+    pub moves: Vec<MoveReport>,
+    pub best_move: Option<usize>,
+
+    /// Line of play the search expects from the position, starting with `best_move`.
+    pub principal_variation: Vec<usize>,
+
+    /// Simulations run, and how many of them the player to move won, lost and drew.
+    pub iterations: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+
+    /// Positions held in memory once the search was over.
+    pub nodes: usize,
+    pub time: Duration,
+}
+
+impl SearchReport {
+    /// The report as a JSON object with the same field names, where unknown best moves
+    /// are `null` and the time is in `time_ms`.
+    pub fn to_json(&self) -> String {
+        let moves = self.moves.iter()
+            .map(MoveReport::to_json)
+            .collect::<Vec<_>>();
+
+        let line = self.principal_variation.iter().map(|col| col.to_string()).collect::<Vec<_>>();
+
+        format!(
+            r#"{{"moves":[{}],"best_move":{},"principal_variation":[{}],"iterations":{},"wins":{},"losses":{},"draws":{},"nodes":{},"time_ms":{}}}"#,
+            moves.join(","),
+            self.best_move.map_or("null".to_string(), |col| col.to_string()),
+            line.join(","),
+            self.iterations,
+            self.wins,
+            self.losses,
+            self.draws,
+            self.nodes,
+            self.time.as_secs_f64() * 1000.0,
+        )
+    }
+}
+
+/// A table of the moves, with columns numbered from 1 as on the board.
+impl fmt::Display for SearchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, " col  visits  value  interval       proof")?;
+        for report in self.moves.iter() {
+            let best = if Some(report.col) == self.best_move { "*" } else { " " };
+            let row = format!(
+                "{}{:<3} {:>7}  {:.3}  {:.3} - {:.3}  {}",
+                best,
+                report.col + 1,
+                report.visits,
+                report.value,
+                report.interval.0,
+                report.interval.1,
+                proof_name(report.proof).unwrap_or(""),
+            );
+
+            writeln!(f, "{}", row.trim_end())?;
+        }
+
+        let line = self.principal_variation.iter().map(|col| (col + 1).to_string()).collect::<Vec<_>>();
+        writeln!(f, "line: {}", line.join(" "))?;
+        write!(
+            f,
+            "{} simulations (+{} ={} -{}) in {:.2}s, {} positions in memory",
+            self.iterations,
+            self.wins,
+            self.draws,
+            self.losses,
+            self.time.as_secs_f64(),
+            self.nodes,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wilson_interval() {
+        assert_eq!(wilson_interval(0.0, 0), (0.0, 1.0));
+
+        let (low, high) = wilson_interval(60.0, 100);
+        assert!(low < 0.6 && 0.6 < high);
+        assert!((low - 0.502).abs() < 0.001 && (high - 0.691).abs() < 0.001);
+
+        // narrower with more games
+        let (more_low, more_high) = wilson_interval(600.0, 1000);
+        assert!(more_high - more_low < high - low);
+    }
+
+    #[test]
+    fn test_search_report_json() {
+        let report = SearchReport {
+            moves: vec![
+                MoveReport::new(0, (0.0, 0), None),
+                MoveReport::new(3, (3.0, 4), Some(Proof::Win)),
+            ],
+            best_move: Some(3),
+            principal_variation: vec![3, 2],
+            iterations: 4,
+            wins: 3,
+            losses: 1,
+            draws: 0,
+            nodes: 5,
+            time: Duration::from_millis(2),
+        };
+
+        let json = report.to_json();
+        assert!(json.starts_with(r#"{"moves":[{"col":0,"visits":0,"value":0.5,"interval":[0,1],"proof":null},"#));
+        assert!(json.contains(r#""proof":"win"}],"best_move":3,"principal_variation":[3,2],"iterations":4,"#));
+        assert!(json.ends_with(r#""nodes":5,"time_ms":2}"#));
+    }
+}
@@ -0,0 +1,441 @@
+use game::{Game, Key};
+
+/// Number of entries of the transposition table, about 16 MB worth.
+const TABLE_SIZE: usize = 1 << 20;
+
+/// Game-theoretic value of a position for the player to move, along with the number of
+/// plies until the game ends with perfect play.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Outcome {
+    Win(usize),
+    Loss(usize),
+    Draw(usize),
+    /// The search ran out of nodes before proving anything.
+    Unknown,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Solution {
+    pub outcome: Outcome,
+    pub best_move: Option<usize>,
+
+    /// Depth of the last completed iteration.
+    pub depth: usize,
+    pub nodes: usize,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+// kept small since the table holds a million of them
+#[derive(Copy, Clone)]
+struct Entry {
+    key: Key,
+    value: i16,
+    depth: u16,
+    bound: Bound,
+    best_move: Option<u8>,
+}
+
+/// Transposition table of a fixed size, where each entry replaces whatever was stored in
+/// its slot before.
+struct Table {
+    entries: Vec<Option<Entry>>,
+}
+
+impl Table {
+    fn new(size: usize) -> Table {
+        Table {
+            entries: vec![None; size],
+        }
+    }
+
+    fn slot(&self, key: Key) -> usize {
+        (key % self.entries.len() as Key) as usize
+    }
+
+    fn get(&self, key: Key) -> Option<&Entry> {
+        self.entries[self.slot(key)].as_ref().filter(|entry| entry.key == key)
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        let slot = self.slot(entry.key);
+        self.entries[slot] = Some(entry);
+    }
+
+    fn clear(&mut self) {
+        for entry in self.entries.iter_mut() {
+            *entry = None;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OutOfNodes;
+
+/// Exact solver using negamax with alpha-beta pruning, a transposition table and
+/// iterative deepening, where each iteration narrows down the value with null-window
+/// searches as in MTD(f).
+///
+/// Scores are relative to the player to move and count the plies of the whole game, so
+/// that they do not depend on the path to a position: a win ending the game with `n`
+/// tokens on the board scores `cells + 1 - n`, a loss the opposite and a draw `0`.
+/// Positions beyond the depth of an iteration also score `0`, which never hides a
+/// forced win or loss.
+pub struct Solver {
+    table: Table,
+
+    // columns, rows and win length of the board the table belongs to, since keys don't
+    // tell boards apart
+    shape: Option<(usize, usize, usize)>,
+
+    max_nodes: Option<usize>,
+    nodes: usize,
+}
+
+impl Default for Solver {
+    fn default() -> Solver {
+        Solver::new()
+    }
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver {
+            table: Table::new(TABLE_SIZE),
+            shape: None,
+            max_nodes: None,
+            nodes: 0,
+        }
+    }
+
+    /// Gives up on deeper iterations once a single `solve` has visited `nodes` positions.
+    pub fn with_node_limit(nodes: usize) -> Solver {
+        Solver {
+            max_nodes: Some(nodes),
+            ..Solver::new()
+        }
+    }
+
+    fn base(game: &Game) -> i32 {
+        (game.cols() * game.rows()) as i32 + 1
+    }
+
+    /// Score of winning with the `plies`th move from `game`.
+    fn win(game: &Game, plies: usize) -> i32 {
+        Solver::base(game) - (game.ply() + plies) as i32
+    }
+
+    /// Columns ordered from the center outwards.
+    fn ordered_moves(game: &Game, first: Option<usize>) -> Vec<usize> {
+        let center = (game.cols() as f64 - 1.0) / 2.0;
+        let mut moves = game.valid_moves();
+        moves.sort_by(|a, b| {
+            let a = (*a as f64 - center).abs();
+            let b = (*b as f64 - center).abs();
+            a.partial_cmp(&b).unwrap()
+        });
+
+        if let Some(first) = first {
+            if let Some(i) = moves.iter().position(|col| *col == first) {
+                moves.remove(i);
+                moves.insert(0, first);
+            }
+        }
+
+        moves
+    }
+
+    /// Fail-soft alpha-beta search of `game`, which is played on and restored.
+    fn negamax(&mut self, game: &mut Game, depth: usize, mut alpha: i32, mut beta: i32) -> Result<(i32, Option<usize>), OutOfNodes> {
+        self.nodes += 1;
+        if self.max_nodes.is_some_and(|max| self.nodes > max) {
+            return Err(OutOfNodes)
+        }
+
+        if game.winning_token().is_some() {
+            // the previous player just won
+            return Ok((game.ply() as i32 - Solver::base(game), None))
+        }
+
+        if game.over() || depth == 0 {
+            return Ok((0, None))
+        }
+
+        let mut best_move = None;
+        if let Some(entry) = self.table.get(game.key()) {
+            let result = (entry.value as i32, entry.best_move.map(|col| col as usize));
+            if entry.depth as usize >= depth {
+                match entry.bound {
+                    Bound::Exact => return Ok(result),
+                    Bound::Lower if result.0 >= beta => return Ok(result),
+                    Bound::Upper if result.0 <= alpha => return Ok(result),
+                    _ => (),
+                }
+            }
+
+            best_move = result.1;
+        }
+
+        // take an immediate win without looking any further
+        let mut moves = Solver::ordered_moves(game, best_move);
+        let player = game.current_player();
+        if let Some(col) = moves.iter().find(|col| game.winning_move(**col, player)) {
+            return Ok((Solver::win(game, 1), Some(*col)))
+        }
+
+        // a threat of the opponent has to be blocked, and two of them can't be
+        let opponent = player.other();
+        let threats = moves.iter()
+            .cloned()
+            .filter(|col| game.winning_move(*col, opponent))
+            .collect::<Vec<_>>();
+        if threats.len() > 1 {
+            return Ok((-Solver::win(game, 2), Some(threats[0])))
+        } else if threats.len() == 1 {
+            moves = threats;
+        }
+
+        // nor should a move let the opponent win right on top of it
+        let safe = moves.iter()
+            .cloned()
+            .filter(|col| !game.winning_cell(*col, game.height(*col) + 1, opponent))
+            .collect::<Vec<_>>();
+        if safe.is_empty() {
+            return Ok((-Solver::win(game, 2), Some(moves[0])))
+        }
+
+        // neither player can win before the next but one move from here, while reaching
+        // the horizon scores 0
+        let (min, max) = ((-Solver::win(game, 4)).min(0), Solver::win(game, 3).max(0));
+        if beta > max {
+            beta = max;
+            if alpha >= beta {
+                return Ok((beta, None))
+            }
+        }
+
+        if alpha < min {
+            alpha = min;
+            if alpha >= beta {
+                return Ok((alpha, Some(safe[0])))
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best = (i32::MIN, None);
+        for col in safe {
+            game.drop(col).unwrap();
+            let result = self.negamax(game, depth - 1, -beta, -alpha);
+            game.undo();
+
+            let value = -result?.0;
+            if value > best.0 {
+                best = (value, Some(col));
+            }
+
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break
+            }
+        }
+
+        let bound = if best.0 <= original_alpha {
+            Bound::Upper
+        } else if best.0 >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+
+        self.table.insert(Entry {
+            key: game.key(),
+            value: best.0 as i16,
+            depth: depth as u16,
+            bound,
+            best_move: best.1.map(|col| col as u8),
+        });
+
+        Ok(best)
+    }
+
+    /// Finds the value of `game` searched to `depth` with null windows, starting from
+    /// `guess` and moving the window towards the value until the bounds meet.
+    fn mtdf(&mut self, game: &mut Game, depth: usize, guess: i32) -> Result<(i32, Option<usize>), OutOfNodes> {
+        let bound = Solver::base(game);
+        let (mut lower, mut upper) = (-bound, bound);
+        let (mut value, mut best_move) = (guess, None);
+        while lower < upper {
+            let beta = if value == lower { value + 1 } else { value };
+            let result = self.negamax(game, depth, beta - 1, beta)?;
+            value = result.0;
+
+            // only searches failing high prove that their move reaches the value
+            if value < beta {
+                upper = value;
+            } else {
+                lower = value;
+                best_move = result.1;
+            }
+        }
+
+        Ok((value, best_move))
+    }
+
+    fn outcome(game: &Game, value: i32) -> Outcome {
+        let base = Solver::base(game);
+        if value > 0 {
+            Outcome::Win((base - value) as usize - game.ply())
+        } else if value < 0 {
+            Outcome::Loss((base + value) as usize - game.ply())
+        } else {
+            Outcome::Draw(game.cols() * game.rows() - game.ply())
+        }
+    }
+
+    /// Searches deeper and deeper until the value of `game` is known, or the node limit
+    /// is reached, in which case the outcome is `Unknown` and the best move comes from
+    /// the last completed iteration.
+    pub fn solve(&mut self, game: &Game) -> Solution {
+        self.nodes = 0;
+
+        let shape = (game.cols(), game.rows(), game.win_len());
+        if self.shape != Some(shape) {
+            self.table.clear();
+            self.shape = Some(shape);
+        }
+
+        let mut solution = Solution {
+            outcome: Outcome::Unknown,
+            best_move: None,
+            depth: 0,
+            nodes: 0,
+        };
+
+        if game.over() {
+            solution.outcome = match game.winning_token() {
+                Some(_) => Outcome::Loss(0),
+                None => Outcome::Draw(0),
+            };
+
+            return solution
+        }
+
+        let mut game = game.clone();
+        let remaining = game.cols() * game.rows() - game.ply();
+        let mut guess = 0;
+        for depth in 1..=remaining {
+            let (value, best_move) = match self.mtdf(&mut game, depth, guess) {
+                Err(OutOfNodes) => break,
+                Ok(result) => result,
+            };
+
+            guess = value;
+
+            solution.best_move = best_move;
+            solution.depth = depth;
+
+            // scores of zero are only exact once the search reaches the end of the game
+            if value != 0 || depth == remaining {
+                solution.outcome = Solver::outcome(&game, value);
+                break
+            }
+        }
+
+        solution.nodes = self.nodes;
+        solution
+    }
+
+    /// Plays the best move found, falling back to the most central one.
+    pub fn next_move(&mut self, game: &Game) -> usize {
+        match self.solve(game).best_move {
+            Some(col) => col,
+            None => Solver::ordered_moves(game, None)[0],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::Rng;
+
+    // plain minimax over the whole game tree
+    fn minimax(game: &Game) -> i32 {
+        if game.winner().is_some() {
+            return game.ply() as i32 - Solver::base(game)
+        }
+
+        if game.over() {
+            return 0
+        }
+
+        game.valid_moves().into_iter()
+            .map(|col| {
+                let mut child = game.clone();
+                child.drop(col).unwrap();
+                -minimax(&child)
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_solver_immediate_win() {
+        let mut game = Game::new();
+        for col in [0, 0, 1, 1, 2, 2].iter() {
+            game.drop(*col).unwrap();
+        }
+
+        let solution = Solver::new().solve(&game);
+        assert_eq!(solution.outcome, Outcome::Win(1));
+        assert_eq!(solution.best_move, Some(3));
+    }
+
+    #[test]
+    fn test_solver_matches_minimax() {
+        let mut rng = Rng::new(10);
+
+        for _ in 0..20 {
+            let mut game = Game::custom(4, 3, 3);
+            for _ in 0..3 + rng.below(5) {
+                if game.over() {
+                    break
+                }
+
+                let moves = game.valid_moves();
+                game.drop(moves[rng.below(moves.len())]).unwrap();
+            }
+
+            if game.over() {
+                continue
+            }
+
+            let expected = Solver::outcome(&game, minimax(&game));
+            assert_eq!(Solver::new().solve(&game).outcome, expected);
+        }
+    }
+
+    #[test]
+    fn test_solver_reused_on_other_boards() {
+        // empty boards all have the same key
+        let mut solver = Solver::new();
+        for (cols, rows, win_len) in [(4, 3, 3), (3, 3, 3), (3, 4, 3), (4, 3, 3)].iter() {
+            let game = Game::custom(*cols, *rows, *win_len);
+            assert_eq!(solver.solve(&game).outcome, Solver::outcome(&game, minimax(&game)));
+        }
+    }
+
+    #[test]
+    fn test_solver_node_limit() {
+        let game = Game::new();
+
+        let solution = Solver::with_node_limit(10_000).solve(&game);
+        assert_eq!(solution.outcome, Outcome::Unknown);
+        assert!(solution.best_move.is_some());
+    }
+}
@@ -104,7 +104,7 @@ export class Game extends EventTarget {
         return new Game(worker, await worker.send('newGame', opts));
     }
 
-    constructor(worker, { id, cols, rows, moves, board }) {
+    constructor(worker, { id, cols, rows, moves, board, ply }) {
         super();
         this.__worker = worker;
         this.__id = id;
@@ -112,6 +112,7 @@ export class Game extends EventTarget {
         this.__rows = rows;
         this.__moves = moves;
         this.__board = convertBoard(board);
+        this.__ply = ply;
         this.__lastMove = null;
         this.__over = false;
     }
@@ -136,19 +137,24 @@ export class Game extends EventTarget {
         return this.__over;
     }
 
+    // number of moves played so far
+    get ply() {
+        return this.__ply;
+    }
+
     async drop(column) {
         if (this.__over) {
             throw new Error('game is already over');
         }
 
-        const { cell, over, cells, moves, board } = await this.__worker.send('drop', { gameId: this.__id, column });
+        const { cell, over, cells, moves, board, ply } = await this.__worker.send('drop', { gameId: this.__id, column });
+        this.__ply = ply;
 
         this.dispatchEvent(new BoardChangedEvent(this.__board = convertBoard(board)));
 
         if (this.__over = over) {
             this.dispatchEvent(new MovesChangedEvent(this.__moves = []));
             this.dispatchEvent(new GameOverEvent(cells ? this.__board[cell[0]][cell[1]] : null, cells));
-            await this.__worker.send('freeGame', { gameId: this.__id });
         } else if (this.__moves.length !== moves.length) {
             this.dispatchEvent(new MovesChangedEvent(this.__moves = moves));
         }
@@ -156,12 +162,34 @@ export class Game extends EventTarget {
         return cell;
     }
 
+    // takes back the last `count` moves, which also resumes a finished game
+    async undo(count = 1) {
+        const { over, moves, board, ply } = await this.__worker.send('undo', { gameId: this.__id, count });
+        this.__over = over;
+        this.__ply = ply;
+
+        this.dispatchEvent(new BoardChangedEvent(this.__board = convertBoard(board)));
+        this.dispatchEvent(new MovesChangedEvent(this.__moves = moves));
+    }
+
     async bestMove(thinkingTime) {
         if (this.__over) {
             throw new Error('game is already over');
         }
 
-        const moves = await this.__worker.send('think', { gameId: this.__id, duration: thinkingTime });
-        return moves.reduce((best, [move, weight]) => !best || best[1] < weight ? [move, weight] : best, null)[0];
+        const { best } = await this.__worker.send('think', { gameId: this.__id, duration: thinkingTime });
+        return best;
+    }
+
+    // lets the bot search in the background until the next move
+    async ponder() {
+        if (!this.__over) {
+            await this.__worker.send('ponder', { gameId: this.__id });
+        }
+    }
+
+    // releases the game in the worker, after which it can't be used
+    async free() {
+        await this.__worker.send('freeGame', { gameId: this.__id });
     }
 }
@@ -1,7 +1,30 @@
-import init, { Game, MCTS } from '../../pkg/libc4';
+import init, { Game, MCTS, SearchLimits } from '../../pkg/libc4';
 
 const games = {};
 
+// simulations run per slice of pondering, between which other messages are handled
+const PONDER_SLICE = 256;
+let pondering = null;
+
+function stopPondering() {
+    if (pondering !== null) {
+        clearTimeout(pondering);
+        pondering = null;
+    }
+}
+
+// makes `gameId` the root of the search unless it already is, and returns the number
+// of simulations carried over
+function follow(gameId) {
+    const game = games[gameId];
+    if (gameId === self.mctsGameId) {
+        return self.mcts.visits(game);
+    }
+
+    self.mctsGameId = gameId;
+    return self.mcts.set_root(game);
+}
+
 function randomString() {
     return Math.random().toString().slice(2);
 }
@@ -19,10 +42,15 @@ const functions = {
             rows: game.rows(),
             moves: game.valid_moves(),
             board: game.board(),
+            ply: game.ply(),
         };
     },
 
     freeGame({ gameId }) {
+        if (gameId === self.mctsGameId) {
+            delete self.mctsGameId;
+        }
+
         games[gameId].free();
         delete games[gameId];
     },
@@ -34,31 +62,82 @@ const functions = {
     
         const game = games[gameId];
         const row = game.drop(column);
+        if (gameId === self.mctsGameId) {
+            // keep only the subtree of the move that was played
+            self.mcts.advance(column);
+        }
+
         return {
             cell: [column, row],
             over: game.over(),
             cells: game.winner_cells(),
             moves: game.valid_moves(),
             board: game.board(),
+            ply: game.ply(),
+        }
+    },
+
+    // takes back up to `count` moves
+    undo({ gameId, count = 1 }) {
+        if (!(gameId in games)) {
+            throw new Error('game not found');
+        }
+
+        const game = games[gameId];
+        for (let i = 0; i < count && game.undo() !== undefined; i++) {}
+        if (gameId === self.mctsGameId) {
+            // earlier positions aren't part of the current root's subtree
+            self.mcts.set_root(game);
+        }
+
+        return {
+            over: game.over(),
+            moves: game.valid_moves(),
+            board: game.board(),
+            ply: game.ply(),
+        };
+    },
+
+    // keeps searching in slices until the next message arrives or the game is decided
+    ponder({ gameId }) {
+        if (!(gameId in games)) {
+            throw new Error('game not found');
         }
+
+        const game = games[gameId];
+        follow(gameId);
+
+        const slice = () => {
+            pondering = self.mcts.think_slice(game, PONDER_SLICE) > 0 ? setTimeout(slice, 0) : null;
+        };
+        pondering = setTimeout(slice, 0);
     },
 
-    think({ gameId, duration = 1000 }) {
+    think({ gameId, duration = 1000, iterations }) {
         if (!(gameId in games)) {
             throw new Error('game not found');
         }
 
         const game = games[gameId];
-        self.mcts.think(game, duration);
+        const reused = follow(gameId);
+        const limits = new SearchLimits(iterations, undefined, duration, true);
+        const report = self.mcts.think(game, limits);
+        limits.free();
 
         const moves = game.valid_moves();
-        const weights = self.mcts.move_weights(game.state(), moves);
-        return moves.map((move, i) => [move, weights[i]]);
+        const weights = self.mcts.move_weights(game, moves);
+        return {
+            best: report.best_move,
+            reused,
+            report,
+            weights: moves.map((move, i) => [move, weights[i]]),
+        };
     }
 };
 
 onmessage = async (event) => {
     const { id, name, payload } = event.data;
+    stopPondering();
 
     try {
         postMessage({ id, result: await functions[name](payload) });
@@ -67,4 +146,15 @@ onmessage = async (event) => {
     }
 }
 
-init('mcts_bg.wasm').then(() => (self.mcts = new MCTS(), postMessage({ ready: true })), (error) => postMessage({ error }))
+// keep the search tree from growing until the tab runs out of memory
+const MAX_MEMORY_BYTES = 64 * 1024 * 1024;
+
+function createMCTS() {
+    const mcts = new MCTS();
+    mcts.set_max_bytes(MAX_MEMORY_BYTES);
+    // the worker runs few enough simulations per move for RAVE to pay off
+    mcts.use_rave();
+    return mcts;
+}
+
+init('mcts_bg.wasm').then(() => (self.mcts = createMCTS(), postMessage({ ready: true })), (error) => postMessage({ error }))